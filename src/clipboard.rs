@@ -0,0 +1,29 @@
+use std::io;
+
+use base64::Engine;
+
+/// Copy the given text to the system clipboard by asking the terminal to do so
+/// via the OSC 52 escape sequence. This works over SSH too because it doesn't
+/// need access to the clipboard of the machine `jv` is running on, but it
+/// requires a terminal that supports it.
+pub fn copy(term: &mut impl io::Write, text: &str) -> io::Result<()> {
+    write!(
+        term,
+        "\x1b]52;c;{}\x07",
+        base64::engine::general_purpose::STANDARD.encode(text)
+    )?;
+    term.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::copy;
+
+    #[test]
+    fn test_copy() {
+        let mut out = vec![];
+        copy(&mut out, "#/items/0").unwrap();
+
+        assert_eq!(out, b"\x1b]52;c;Iy9pdGVtcy8w\x07");
+    }
+}
@@ -0,0 +1,239 @@
+//! BSON documents converted to JSON values, one per document of the file.
+
+use bson::spec::BinarySubtype;
+use bson::Bson;
+use serde_json::{Map, Value};
+
+use super::{binary, child, datetime, float, rfc3339, stream, type_name};
+use crate::json::Annotation;
+
+pub use bson::error::Error;
+
+/// Parse all the documents of the given file, like a dump of a collection. The
+/// values whose type JSON doesn't have, like the ObjectIds, are annotated with
+/// their type.
+pub fn documents(input: &[u8]) -> Result<(Vec<Value>, Vec<Annotation>), Error> {
+    let mut rd = input;
+    let mut values = vec![];
+
+    while !rd.is_empty() {
+        let document = bson::Document::from_reader(&mut rd)?;
+
+        let mut annotations = vec![];
+        let value = convert(Bson::Document(document), "", &mut annotations);
+        values.push((value, annotations));
+    }
+
+    Ok(stream(values))
+}
+
+fn convert(value: Bson, path: &str, annotations: &mut Vec<Annotation>) -> Value {
+    match value {
+        Bson::Null => Value::Null,
+        Bson::Boolean(b) => Value::Bool(b),
+        Bson::Int32(i) => Value::from(i),
+        Bson::Int64(i) => Value::from(i),
+        Bson::Double(f) => float(f),
+        Bson::String(s) => Value::String(s),
+        Bson::Binary(b) if b.subtype == BinarySubtype::Uuid && b.bytes.len() == 16 => {
+            type_name(path, "UUID", annotations);
+            Value::String(uuid(&b.bytes))
+        }
+        Bson::Binary(b) => {
+            if b.subtype != BinarySubtype::Generic {
+                type_name(
+                    path,
+                    format!("Binary({})", u8::from(b.subtype)),
+                    annotations,
+                );
+            }
+            binary(&b.bytes, path, annotations)
+        }
+        Bson::ObjectId(id) => {
+            type_name(path, "ObjectId", annotations);
+            Value::String(id.to_hex())
+        }
+        Bson::DateTime(dt) => {
+            let millis = dt.timestamp_millis();
+
+            type_name(path, "Date", annotations);
+            datetime(path, annotations);
+            Value::String(rfc3339(
+                millis.div_euclid(1000),
+                millis.rem_euclid(1000) as u32 * 1_000_000,
+            ))
+        }
+        Bson::Timestamp(ts) => {
+            type_name(path, "Timestamp", annotations);
+            serde_json::json!({"t": ts.time, "i": ts.increment})
+        }
+        Bson::RegularExpression(re) => {
+            type_name(path, "Regex", annotations);
+            Value::String(re.to_string())
+        }
+        Bson::JavaScriptCode(code) => {
+            type_name(path, "Code", annotations);
+            Value::String(code)
+        }
+        Bson::JavaScriptCodeWithScope(code) => {
+            type_name(path, "Code", annotations);
+
+            let mut map = Map::new();
+            map.insert("code".to_string(), Value::String(code.code));
+            map.insert(
+                "scope".to_string(),
+                convert(
+                    Bson::Document(code.scope),
+                    &child(path, "scope"),
+                    annotations,
+                ),
+            );
+            Value::Object(map)
+        }
+        Bson::Symbol(s) => {
+            type_name(path, "Symbol", annotations);
+            Value::String(s)
+        }
+        Bson::Decimal128(d) => {
+            type_name(path, "Decimal128", annotations);
+            Value::String(d.to_string())
+        }
+        Bson::Undefined => {
+            type_name(path, "Undefined", annotations);
+            Value::Null
+        }
+        Bson::MinKey => {
+            type_name(path, "MinKey", annotations);
+            Value::Null
+        }
+        Bson::MaxKey => {
+            type_name(path, "MaxKey", annotations);
+            Value::Null
+        }
+        Bson::DbPointer(_) => {
+            type_name(path, "DBPointer", annotations);
+            Value::Null
+        }
+        Bson::Array(items) => Value::Array(
+            items
+                .into_iter()
+                .enumerate()
+                .map(|(i, v)| convert(v, &child(path, &i.to_string()), annotations))
+                .collect(),
+        ),
+        Bson::Document(document) => Value::Object(
+            document
+                .into_iter()
+                .map(|(k, v)| {
+                    let v = convert(v, &child(path, &k), annotations);
+                    (k, v)
+                })
+                .collect::<Map<_, _>>(),
+        ),
+    }
+}
+
+/// Format the 16 bytes of a UUID like `123e4567-e89b-12d3-a456-426614174000`.
+fn uuid(bytes: &[u8]) -> String {
+    let hex = bytes
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect::<String>();
+
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use bson::oid::ObjectId;
+    use bson::raw::CString;
+    use bson::spec::BinarySubtype;
+    use bson::{doc, Binary, Bson, DateTime, Regex};
+    use serde_json::json;
+
+    use super::documents;
+    use crate::json::JsonTokenTag;
+
+    #[test]
+    fn test_documents() {
+        let first = doc! {
+            "_id": ObjectId::parse_str("5f1d7f3e9d1b2c3a4b5c6d7e").unwrap(),
+            "name": "jv",
+            "tags": [1, 2_i64, 1.5, Bson::Null, true],
+            "created": DateTime::from_millis(1_582_934_400_500),
+            "data": Binary { subtype: BinarySubtype::Generic, bytes: b"hi".to_vec() },
+        };
+        let second = doc! {
+            "id": Binary {
+                subtype: BinarySubtype::Uuid,
+                bytes: (0..16).collect(),
+            },
+            "re": Regex {
+                pattern: CString::try_from("^a").unwrap(),
+                options: CString::try_from("i").unwrap(),
+            },
+            "min": Bson::MinKey,
+        };
+
+        let mut input = vec![];
+        first.to_writer(&mut input).unwrap();
+        second.to_writer(&mut input).unwrap();
+
+        let (values, annotations) = documents(&input).unwrap();
+
+        assert_eq!(
+            values,
+            vec![
+                json!({
+                    "_id": "5f1d7f3e9d1b2c3a4b5c6d7e",
+                    "name": "jv",
+                    "tags": [1, 2, 1.5, null, true],
+                    "created": "2020-02-29T00:00:00.5Z",
+                    "data": "aGk=",
+                }),
+                json!({
+                    "id": "00010203-0405-0607-0809-0a0b0c0d0e0f",
+                    "re": "/^a/i",
+                    "min": null,
+                }),
+            ]
+        );
+        assert_eq!(
+            annotations
+                .iter()
+                .map(|a| (
+                    a.pointer.as_str(),
+                    a.tag,
+                    a.text.as_deref(),
+                    a.type_name.as_deref()
+                ))
+                .collect::<Vec<_>>(),
+            vec![
+                ("/0/_id", None, None, Some("ObjectId")),
+                ("/0/created", None, None, Some("Date")),
+                ("/0/created", Some(JsonTokenTag::DateTime), None, None),
+                (
+                    "/0/data",
+                    Some(JsonTokenTag::Binary),
+                    Some("<6869 2 bytes>"),
+                    None
+                ),
+                ("/1/id", None, None, Some("UUID")),
+                ("/1/re", None, None, Some("Regex")),
+                ("/1/min", None, None, Some("MinKey")),
+            ]
+        );
+
+        assert!(documents(b"").unwrap().0.is_empty());
+        assert!(documents(&input[..input.len() - 1]).is_err());
+    }
+}
@@ -0,0 +1,188 @@
+//! CBOR sequences converted to JSON values, one per top level item.
+
+use std::convert::TryFrom;
+use std::io;
+
+use serde_json::{Map, Value};
+
+use super::{binary, child, datetime, float, key_string, rfc3339, stream, type_name};
+use crate::json::Annotation;
+
+pub type Error = ciborium::de::Error<io::Error>;
+
+/// Tag of the datetimes as RFC 3339 strings.
+const DATETIME: u64 = 0;
+
+/// Tag of the datetimes as the seconds since the Unix epoch.
+const EPOCH: u64 = 1;
+
+/// Tag that marks the data as CBOR, it means nothing else.
+const SELF_DESCRIBED: u64 = 55_799;
+
+/// Parse all the items of the given CBOR sequence. The byte strings and the
+/// tagged items are annotated with their preview and with their tag.
+pub fn documents(input: &[u8]) -> Result<(Vec<Value>, Vec<Annotation>), Error> {
+    let mut rd = input;
+    let mut values = vec![];
+
+    while !rd.is_empty() {
+        let value = ciborium::from_reader(&mut rd)?;
+
+        let mut annotations = vec![];
+        let value = convert(value, "", &mut annotations);
+        values.push((value, annotations));
+    }
+
+    Ok(stream(values))
+}
+
+fn convert(value: ciborium::Value, path: &str, annotations: &mut Vec<Annotation>) -> Value {
+    match value {
+        ciborium::Value::Null => Value::Null,
+        ciborium::Value::Bool(b) => Value::Bool(b),
+        ciborium::Value::Integer(i) => {
+            let i = i128::from(i);
+            match (i64::try_from(i), u64::try_from(i)) {
+                (Ok(i), _) => Value::from(i),
+                (_, Ok(u)) => Value::from(u),
+
+                // only the negative integers below -2^64 don't fit in JSON
+                _ => Value::String(i.to_string()),
+            }
+        }
+        ciborium::Value::Float(f) => float(f),
+        ciborium::Value::Text(s) => Value::String(s),
+        ciborium::Value::Bytes(bytes) => binary(&bytes, path, annotations),
+        ciborium::Value::Tag(SELF_DESCRIBED, value) => convert(*value, path, annotations),
+        ciborium::Value::Tag(DATETIME, value) if value.is_text() => {
+            datetime(path, annotations);
+            convert(*value, path, annotations)
+        }
+        ciborium::Value::Tag(EPOCH, value) if is_epoch(&value) => {
+            let secs = match *value {
+                ciborium::Value::Integer(i) => i128::from(i) as f64,
+                ciborium::Value::Float(f) => f,
+                _ => unreachable!(),
+            };
+
+            type_name(path, format!("tag({})", EPOCH), annotations);
+            datetime(path, annotations);
+
+            let whole = secs.floor();
+            Value::String(rfc3339(whole as i64, ((secs - whole) * 1e9) as u32))
+        }
+        ciborium::Value::Tag(tag, value) => {
+            type_name(path, format!("tag({})", tag), annotations);
+            convert(*value, path, annotations)
+        }
+        ciborium::Value::Array(items) => Value::Array(
+            items
+                .into_iter()
+                .enumerate()
+                .map(|(i, v)| convert(v, &child(path, &i.to_string()), annotations))
+                .collect(),
+        ),
+        ciborium::Value::Map(entries) => Value::Object(
+            entries
+                .into_iter()
+                .map(|(k, v)| {
+                    // the keys can be anything, but only strings can be shown
+                    let k = key_string(convert(k, "", &mut vec![]));
+                    let v = convert(v, &child(path, &k), annotations);
+                    (k, v)
+                })
+                .collect::<Map<_, _>>(),
+        ),
+
+        // the value is non exhaustive
+        _ => Value::Null,
+    }
+}
+
+/// Whether the value of an epoch tag is a number of seconds a datetime can be
+/// made of. NaN, the infinities and the numbers out of the range of `i64` are
+/// tagged values like any other.
+fn is_epoch(value: &ciborium::Value) -> bool {
+    let secs = match *value {
+        ciborium::Value::Integer(i) => i128::from(i) as f64,
+        ciborium::Value::Float(f) => f,
+        _ => return false,
+    };
+
+    secs.is_finite() && (i64::MIN as f64..i64::MAX as f64).contains(&secs)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::documents;
+    use crate::json::JsonTokenTag;
+
+    #[test]
+    fn test_documents() {
+        let input = [
+            // {"name": "jv", "tags": [1, -1, 1.5, null, true], 7: h'6869'}
+            &b"\xa3\x64name\x62jv\x64tags\x85\x01\x20\xf9\x3e\x00\xf6\xf5\x07\x42hi"[..],
+            // [0("2020-02-29T00:00:00Z"), 1(60.5), 32("http://a")]
+            b"\x83\xc0\x742020-02-29T00:00:00Z\xc1\xfb\x40\x4e\x40\0\0\0\0\0\xd8\x20\x68http://a",
+            // a self described item
+            b"\xd9\xd9\xf7\x3b\xff\xff\xff\xff\xff\xff\xff\xff",
+        ]
+        .concat();
+
+        let (values, annotations) = documents(&input).unwrap();
+
+        assert_eq!(
+            values,
+            vec![
+                json!({"name": "jv", "tags": [1, -1, 1.5, null, true], "7": "aGk="}),
+                json!(["2020-02-29T00:00:00Z", "1970-01-01T00:01:00.5Z", "http://a"]),
+                json!("-18446744073709551616"),
+            ]
+        );
+        assert_eq!(
+            annotations
+                .iter()
+                .map(|a| (
+                    a.pointer.as_str(),
+                    a.tag,
+                    a.text.as_deref(),
+                    a.type_name.as_deref()
+                ))
+                .collect::<Vec<_>>(),
+            vec![
+                (
+                    "/0/7",
+                    Some(JsonTokenTag::Binary),
+                    Some("<6869 2 bytes>"),
+                    None
+                ),
+                ("/1/0", Some(JsonTokenTag::DateTime), None, None),
+                ("/1/1", None, None, Some("tag(1)")),
+                ("/1/1", Some(JsonTokenTag::DateTime), None, None),
+                ("/1/2", None, None, Some("tag(32)")),
+            ]
+        );
+
+        assert!(documents(b"").unwrap().0.is_empty());
+        assert!(documents(b"\x82\x01").is_err());
+    }
+
+    #[test]
+    fn test_invalid_epoch() {
+        // [1(NaN), 1(-Infinity), 1(18446744073709551615)]
+        let input = b"\x83\xc1\xfb\x7f\xf8\0\0\0\0\0\0\xc1\xf9\xfc\x00\xc1\x1b\xff\xff\xff\xff\xff\xff\xff\xff";
+
+        let (values, annotations) = documents(input).unwrap();
+
+        assert_eq!(
+            values,
+            vec![json!(["NaN", "-inf", 18446744073709551615u64])]
+        );
+        assert!(annotations
+            .iter()
+            .all(|a| a.tag.is_none() && a.type_name.as_deref() == Some("tag(1)")));
+        assert_eq!(annotations.len(), 3);
+    }
+}
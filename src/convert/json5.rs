@@ -0,0 +1,622 @@
+//! JSON5 documents, and JSON documents with comments like the settings of many
+//! editors, converted to JSON values. Their comments are kept too.
+
+use std::convert::TryFrom;
+use std::fmt;
+
+use serde_json::{Map, Number, Value};
+
+use super::{child, float};
+use crate::json::{Comment, CommentPosition};
+
+/// Why a document isn't valid JSON5 and where, the line and the column start
+/// from 1 and the column is in bytes like for `serde_json`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Error {
+    pub message: String,
+    pub line: usize,
+    pub column: usize,
+}
+
+/// Parse the given JSON5 document. It's JSON with comments, trailing commas,
+/// unquoted keys, single quoted strings and a few more kinds of numbers.
+pub fn document(input: &str) -> Result<(Value, Vec<Comment>), Error> {
+    let mut parser = Parser {
+        input,
+        pos: 0,
+        line: (0, 1),
+        comments: vec![],
+    };
+
+    let value = parser.document()?;
+
+    Ok((value, parser.comments))
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize,
+
+    // position and number of the line of the last lookup, the lookups only go
+    // forward
+    line: (usize, usize),
+
+    comments: Vec<Comment>,
+}
+
+impl<'a> Parser<'a> {
+    fn document(&mut self) -> Result<Value, Error> {
+        let comments = self.skip()?;
+        self.attach(
+            comments,
+            ("", CommentPosition::Before),
+            ("", CommentPosition::Before),
+        );
+
+        let value = self.value("")?;
+
+        let comments = self.skip()?;
+        self.attach(
+            comments,
+            ("", CommentPosition::End),
+            ("", CommentPosition::After),
+        );
+
+        match self.peek() {
+            None => Ok(value),
+            Some(c) => Err(self.error(format!("unexpected `{}` after the document", c))),
+        }
+    }
+
+    fn value(&mut self, path: &str) -> Result<Value, Error> {
+        match self.peek() {
+            Some('{') => self.object(path),
+            Some('[') => self.array(path),
+            Some(q @ '"') | Some(q @ '\'') => self.string(q).map(Value::String),
+            Some(c) if c == '-' || c == '+' || c == '.' || c.is_ascii_digit() => self.number(),
+            Some(c) if is_identifier_start(c) => {
+                let start = self.pos;
+
+                match self.identifier() {
+                    "true" => Ok(Value::Bool(true)),
+                    "false" => Ok(Value::Bool(false)),
+                    "null" => Ok(Value::Null),
+                    "Infinity" => Ok(float(f64::INFINITY)),
+                    "NaN" => Ok(float(f64::NAN)),
+                    ident => {
+                        let ident = ident.to_string();
+                        self.pos = start;
+                        Err(self.error(format!("unexpected `{}`", ident)))
+                    }
+                }
+            }
+            Some(c) => Err(self.error(format!("unexpected `{}`", c))),
+            None => Err(self.error("unexpected end of input")),
+        }
+    }
+
+    fn object(&mut self, path: &str) -> Result<Value, Error> {
+        self.pos += 1;
+
+        let mut map = Map::new();
+
+        // the comments on the same line as the previous token are shown at
+        // the end of its line
+        let mut prev = (path.to_string(), CommentPosition::Start);
+
+        loop {
+            let comments = self.skip()?;
+            if self.peek() == Some('}') {
+                self.attach(
+                    comments,
+                    (&prev.0, prev.1),
+                    (path, CommentPosition::BeforeEnd),
+                );
+                self.pos += 1;
+                return Ok(Value::Object(map));
+            }
+
+            let key = self.key()?;
+            let pointer = child(path, &key);
+            self.attach(
+                comments,
+                (&prev.0, prev.1),
+                (&pointer, CommentPosition::Before),
+            );
+
+            let mut comments = self.skip()?;
+            self.expect(':')?;
+            comments.extend(self.skip()?);
+            self.attach(
+                comments,
+                (&pointer, CommentPosition::Start),
+                (&pointer, CommentPosition::Start),
+            );
+
+            let value = self.value(&pointer)?;
+            map.insert(key, value);
+
+            let comments = self.skip()?;
+            match self.peek() {
+                Some(',') => {
+                    self.attach(
+                        comments,
+                        (&pointer, CommentPosition::End),
+                        (&pointer, CommentPosition::End),
+                    );
+                    self.pos += 1;
+                }
+                Some('}') => {
+                    self.attach(
+                        comments,
+                        (&pointer, CommentPosition::End),
+                        (path, CommentPosition::BeforeEnd),
+                    );
+                    self.pos += 1;
+                    return Ok(Value::Object(map));
+                }
+                _ => return Err(self.error("expected `,` or `}`")),
+            }
+
+            prev = (pointer, CommentPosition::End);
+        }
+    }
+
+    fn array(&mut self, path: &str) -> Result<Value, Error> {
+        self.pos += 1;
+
+        let mut items = vec![];
+        let mut prev = (path.to_string(), CommentPosition::Start);
+
+        loop {
+            let comments = self.skip()?;
+            if self.peek() == Some(']') {
+                self.attach(
+                    comments,
+                    (&prev.0, prev.1),
+                    (path, CommentPosition::BeforeEnd),
+                );
+                self.pos += 1;
+                return Ok(Value::Array(items));
+            }
+
+            let pointer = child(path, &items.len().to_string());
+            self.attach(
+                comments,
+                (&prev.0, prev.1),
+                (&pointer, CommentPosition::Before),
+            );
+
+            items.push(self.value(&pointer)?);
+
+            let comments = self.skip()?;
+            match self.peek() {
+                Some(',') => {
+                    self.attach(
+                        comments,
+                        (&pointer, CommentPosition::End),
+                        (&pointer, CommentPosition::End),
+                    );
+                    self.pos += 1;
+                }
+                Some(']') => {
+                    self.attach(
+                        comments,
+                        (&pointer, CommentPosition::End),
+                        (path, CommentPosition::BeforeEnd),
+                    );
+                    self.pos += 1;
+                    return Ok(Value::Array(items));
+                }
+                _ => return Err(self.error("expected `,` or `]`")),
+            }
+
+            prev = (pointer, CommentPosition::End);
+        }
+    }
+
+    fn key(&mut self) -> Result<String, Error> {
+        match self.peek() {
+            Some(q @ '"') | Some(q @ '\'') => self.string(q),
+            Some(c) if is_identifier_start(c) => Ok(self.identifier().to_string()),
+            Some(c) => Err(self.error(format!("expected a key, found `{}`", c))),
+            None => Err(self.error("unexpected end of input")),
+        }
+    }
+
+    fn identifier(&mut self) -> &'a str {
+        let rest = &self.input[self.pos..];
+        let len = rest
+            .char_indices()
+            .find(|&(i, c)| !(is_identifier_start(c) || (i > 0 && is_identifier_part(c))))
+            .map_or(rest.len(), |(i, _)| i);
+
+        self.pos += len;
+        &rest[..len]
+    }
+
+    fn string(&mut self, quote: char) -> Result<String, Error> {
+        self.pos += 1;
+
+        let mut s = String::new();
+        loop {
+            let c = match self.next() {
+                Some(c) => c,
+                None => return Err(self.error("unterminated string")),
+            };
+
+            match c {
+                _ if c == quote => return Ok(s),
+                '\n' | '\r' => {
+                    self.pos -= 1;
+                    return Err(self.error("unterminated string"));
+                }
+                '\\' => {
+                    let escape = self
+                        .next()
+                        .ok_or_else(|| self.error("unterminated string"))?;
+
+                    match escape {
+                        'b' => s.push('\u{8}'),
+                        'f' => s.push('\u{c}'),
+                        'n' => s.push('\n'),
+                        'r' => s.push('\r'),
+                        't' => s.push('\t'),
+                        'v' => s.push('\u{b}'),
+                        '0' => s.push('\0'),
+                        'x' => s.push(self.hex_escape(2)?),
+                        'u' => s.push(self.unicode_escape()?),
+
+                        // a line continuation
+                        '\n' | '\u{2028}' | '\u{2029}' => {}
+                        '\r' => {
+                            if self.peek() == Some('\n') {
+                                self.pos += 1;
+                            }
+                        }
+                        c => s.push(c),
+                    }
+                }
+                c => s.push(c),
+            }
+        }
+    }
+
+    fn hex_escape(&mut self, len: usize) -> Result<char, Error> {
+        let code = self.hex(len)?;
+        char::from_u32(code).ok_or_else(|| self.error("invalid escape"))
+    }
+
+    /// Parse the 4 hex digits of a `\u` escape, a surrogate pair is made of two
+    /// escapes.
+    fn unicode_escape(&mut self) -> Result<char, Error> {
+        let high = self.hex(4)?;
+
+        let code = if (0xd800..0xdc00).contains(&high) && self.input[self.pos..].starts_with("\\u")
+        {
+            self.pos += 2;
+            let low = self.hex(4)?;
+            if !(0xdc00..0xe000).contains(&low) {
+                return Err(self.error("invalid escape"));
+            }
+
+            0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00)
+        } else {
+            high
+        };
+
+        char::from_u32(code).ok_or_else(|| self.error("invalid escape"))
+    }
+
+    fn hex(&mut self, len: usize) -> Result<u32, Error> {
+        let digits = self
+            .input
+            .get(self.pos..self.pos + len)
+            .filter(|d| d.chars().all(|c| c.is_ascii_hexdigit()))
+            .ok_or_else(|| self.error("invalid escape"))?;
+
+        self.pos += len;
+        Ok(u32::from_str_radix(digits, 16).unwrap())
+    }
+
+    fn number(&mut self) -> Result<Value, Error> {
+        let start = self.pos;
+
+        let negative = match self.peek() {
+            Some(c @ '-') | Some(c @ '+') => {
+                self.pos += 1;
+                c == '-'
+            }
+            _ => false,
+        };
+        let sign = if negative { -1.0 } else { 1.0 };
+
+        if self.peek().is_some_and(is_identifier_start) {
+            return match self.identifier() {
+                "Infinity" => Ok(float(sign * f64::INFINITY)),
+                "NaN" => Ok(float(f64::NAN)),
+                _ => {
+                    self.pos = start;
+                    Err(self.error("invalid number"))
+                }
+            };
+        }
+
+        let rest = &self.input[self.pos..];
+        if rest.starts_with("0x") || rest.starts_with("0X") {
+            self.pos += 2;
+            let digits = self.take_while(|c| c.is_ascii_hexdigit());
+
+            let n = match u64::from_str_radix(digits, 16) {
+                Ok(n) => n,
+                Err(_) => {
+                    self.pos = start;
+                    return Err(self.error("invalid number"));
+                }
+            };
+
+            return Ok(match (negative, i64::try_from(n)) {
+                (false, _) => Value::from(n),
+                (true, Ok(n)) => Value::from(-n),
+                (true, Err(_)) => float(-(n as f64)),
+            });
+        }
+
+        // JSON wants digits on both sides of the dot
+        let int = self.take_while(|c| c.is_ascii_digit()).to_string();
+        let mut frac = None;
+        if self.peek() == Some('.') {
+            self.pos += 1;
+            frac = Some(self.take_while(|c| c.is_ascii_digit()));
+        }
+
+        let mut exp = String::new();
+        if let Some(e @ 'e') | Some(e @ 'E') = self.peek() {
+            self.pos += 1;
+            exp.push(e);
+
+            if let Some(s @ '+') | Some(s @ '-') = self.peek() {
+                self.pos += 1;
+                exp.push(s);
+            }
+            exp.push_str(self.take_while(|c| c.is_ascii_digit()));
+        }
+
+        let mut number = String::new();
+        if negative {
+            number.push('-');
+        }
+        number.push_str(if int.is_empty() { "0" } else { &int });
+        if let Some(frac) = frac {
+            number.push('.');
+            number.push_str(if frac.is_empty() { "0" } else { frac });
+        }
+        number.push_str(&exp);
+
+        let has_digits = !int.is_empty() || frac.is_some_and(|f| !f.is_empty());
+        match number.parse::<Number>() {
+            Ok(n) if has_digits => Ok(Value::Number(n)),
+            _ => {
+                self.pos = start;
+                Err(self.error("invalid number"))
+            }
+        }
+    }
+
+    /// Skip the whitespace and the comments, the comments are returned along
+    /// with whether they start on the line where the skipping started.
+    fn skip(&mut self) -> Result<Vec<(String, bool)>, Error> {
+        let line = self.line_at(self.pos);
+        let mut comments = vec![];
+
+        loop {
+            let rest = &self.input[self.pos..];
+
+            let len = if rest.starts_with("//") {
+                rest.find('\n').unwrap_or(rest.len())
+            } else if let Some(block) = rest.strip_prefix("/*") {
+                match block.find("*/") {
+                    Some(i) => i + 4,
+                    None => return Err(self.error("unterminated comment")),
+                }
+            } else {
+                match rest.chars().next() {
+                    Some(c) if c.is_whitespace() || c == '\u{feff}' => {
+                        self.pos += c.len_utf8();
+                        continue;
+                    }
+                    _ => return Ok(comments),
+                }
+            };
+
+            let same_line = self.line_at(self.pos) == line;
+            comments.push((rest[..len].trim_end().to_string(), same_line));
+            self.pos += len;
+        }
+    }
+
+    /// Keep the given comments, those on the same line as the previous token
+    /// and those on their own lines go in different places.
+    fn attach(
+        &mut self,
+        comments: Vec<(String, bool)>,
+        same_line: (&str, CommentPosition),
+        own_line: (&str, CommentPosition),
+    ) {
+        for (text, same) in comments {
+            let (pointer, position) = if same { same_line } else { own_line };
+
+            self.comments.push(Comment {
+                pointer: pointer.to_string(),
+                position,
+                text,
+            });
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), Error> {
+        match self.peek() {
+            Some(n) if n == c => {
+                self.pos += 1;
+                Ok(())
+            }
+            Some(n) => Err(self.error(format!("expected `{}`, found `{}`", c, n))),
+            None => Err(self.error(format!("expected `{}`", c))),
+        }
+    }
+
+    fn take_while(&mut self, f: impl Fn(char) -> bool) -> &'a str {
+        let rest = &self.input[self.pos..];
+        let len = rest.find(|c| !f(c)).unwrap_or(rest.len());
+
+        self.pos += len;
+        &rest[..len]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.input[self.pos..].chars().next()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn line_at(&mut self, pos: usize) -> usize {
+        let (from, line) = if pos >= self.line.0 {
+            self.line
+        } else {
+            (0, 1)
+        };
+
+        let line = line + self.input[from..pos].matches('\n').count();
+        self.line = (pos, line);
+
+        line
+    }
+
+    fn error(&mut self, message: impl Into<String>) -> Error {
+        let line_start = self.input[..self.pos].rfind('\n').map_or(0, |i| i + 1);
+
+        Error {
+            message: message.into(),
+            line: self.line_at(self.pos),
+            column: self.pos - line_start + 1,
+        }
+    }
+}
+
+fn is_identifier_start(c: char) -> bool {
+    c == '$' || c == '_' || c.is_alphabetic()
+}
+
+fn is_identifier_part(c: char) -> bool {
+    is_identifier_start(c) || c.is_alphanumeric() || c == '\u{200c}' || c == '\u{200d}'
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} at line {} column {}",
+            self.message, self.line, self.column
+        )
+    }
+}
+
+impl std::error::Error for Error {}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{document, Error};
+    use crate::json::{add_comments, parse_json, KeyOrder};
+    use crate::widgets::view::Line;
+
+    #[test]
+    fn test_document() {
+        let input = r#"
+{
+  unquoted: 'single "quoted"',
+  $id_1: "line \
+continued\x41è😀",
+  numbers: [+1, -0x1F, .5, 5., 1e3, Infinity, -Infinity, NaN,],
+  "nested": {a: null, b: [true, false,],},
+}
+"#;
+
+        assert_eq!(
+            document(input).unwrap().0,
+            json!({
+                "unquoted": "single \"quoted\"",
+                "$id_1": "line continuedAè😀",
+                "numbers": [1, -31, 0.5, 5.0, 1000.0, "inf", "-inf", "NaN"],
+                "nested": {"a": null, "b": [true, false]},
+            })
+        );
+
+        assert_eq!(document("[1,,]").unwrap_err().column, 4);
+        assert_eq!(
+            document("{\n  a: 1\n  b: 2\n}").unwrap_err(),
+            Error {
+                message: "expected `,` or `}`".to_string(),
+                line: 3,
+                column: 3,
+            }
+        );
+        assert!(document("{a: 1} 2").is_err());
+        assert!(document("'unterminated").is_err());
+        assert!(document("/* unterminated").is_err());
+        assert!(document("[.]").is_err());
+        assert!(document("undefined").is_err());
+
+        assert_eq!(document(r#""\ud83d\ude00""#).unwrap().0, json!("😀"));
+        assert!(document(r#""\ud800\u0041""#).is_err());
+        assert!(document(r#""\ud800""#).is_err());
+    }
+
+    #[test]
+    fn test_comments() {
+        let input = r#"// tsconfig
+{
+  "compilerOptions": { // options
+    /* the target
+     * of the build */
+    "target": "es2020", // modern
+    "strict": true /* always */
+    // end of the options
+  },
+  "files": [
+    "a.ts",
+  ],
+}
+// the end
+"#;
+
+        let (value, comments) = document(input).unwrap();
+        let mut lines = parse_json(value, KeyOrder::Sorted);
+        add_comments(&mut lines, comments);
+
+        assert_eq!(
+            lines.iter().map(|l| l.text()).collect::<Vec<_>>(),
+            vec![
+                "// tsconfig",
+                "{",
+                "    \"compilerOptions\": { // options",
+                "        \"strict\": true, /* always */",
+                "        /* the target",
+                "         * of the build */",
+                "        \"target\": \"es2020\" // modern",
+                "        // end of the options",
+                "    },",
+                "    \"files\": [",
+                "        \"a.ts\"",
+                "    ]",
+                "}",
+                "// the end",
+            ]
+        );
+    }
+}
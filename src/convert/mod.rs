@@ -0,0 +1,208 @@
+//! Conversion of the formats other than JSON into JSON values so that they can
+//! be viewed, navigated and queried exactly like JSON documents.
+
+use base64::Engine;
+use serde_json::{Number, Value};
+
+use crate::json::{pointer, Annotation, JsonTokenTag};
+
+pub mod bson;
+pub mod cbor;
+pub mod json5;
+pub mod msgpack;
+pub mod toml;
+pub mod yaml;
+
+/// Number of bytes of a binary blob shown in its preview.
+const PREVIEW_BYTES: usize = 16;
+
+/// Pointer of the value with the given key or index inside the one at `path`.
+fn child(path: &str, key: &str) -> String {
+    format!("{}/{}", path, pointer::escape(key))
+}
+
+/// Convert a binary blob to its base64 encoding, it's shown as a preview of
+/// its first bytes in hex along with its size.
+fn binary(bytes: &[u8], path: &str, annotations: &mut Vec<Annotation>) -> Value {
+    let hex = bytes
+        .iter()
+        .take(PREVIEW_BYTES)
+        .map(|b| format!("{:02x}", b))
+        .collect::<String>();
+    let more = if bytes.len() > PREVIEW_BYTES {
+        "…"
+    } else {
+        ""
+    };
+
+    annotations.push(Annotation {
+        pointer: path.to_string(),
+        tag: Some(JsonTokenTag::Binary),
+        text: Some(format!("<{}{} {} bytes>", hex, more, bytes.len())),
+        ..Annotation::default()
+    });
+
+    Value::String(base64::engine::general_purpose::STANDARD.encode(bytes))
+}
+
+/// Show the value at the given pointer with the name of its original type.
+fn type_name(path: &str, name: impl Into<String>, annotations: &mut Vec<Annotation>) {
+    annotations.push(Annotation {
+        pointer: path.to_string(),
+        type_name: Some(name.into()),
+        ..Annotation::default()
+    });
+}
+
+/// Highlight the value at the given pointer as a datetime.
+fn datetime(path: &str, annotations: &mut Vec<Annotation>) {
+    annotations.push(Annotation {
+        pointer: path.to_string(),
+        tag: Some(JsonTokenTag::DateTime),
+        ..Annotation::default()
+    });
+}
+
+/// A float as a JSON number, or as a string if it's NaN or infinite since JSON
+/// can't represent them.
+fn float(f: f64) -> Value {
+    match Number::from_f64(f) {
+        Some(n) => Value::Number(n),
+        None => Value::String(f.to_string()),
+    }
+}
+
+/// Object keys must be strings, the other values are used as JSON.
+fn key_string(value: Value) -> String {
+    match value {
+        Value::String(s) => s,
+        v => v.to_string(),
+    }
+}
+
+/// Join the values of a stream, along with their annotations, whose pointers
+/// are prefixed with the index of their value if there's more than one.
+fn stream(values: Vec<(Value, Vec<Annotation>)>) -> (Vec<Value>, Vec<Annotation>) {
+    let many = values.len() > 1;
+    let mut annotations = vec![];
+
+    let values = values
+        .into_iter()
+        .enumerate()
+        .map(|(i, (value, value_annotations))| {
+            annotations.extend(value_annotations.into_iter().map(|mut a| {
+                if many {
+                    a.pointer = format!("/{}{}", i, a.pointer);
+                }
+                a
+            }));
+
+            value
+        })
+        .collect();
+
+    (values, annotations)
+}
+
+/// Format the given time since the Unix epoch as RFC 3339 in UTC.
+fn rfc3339(secs: i64, nanos: u32) -> String {
+    let days = secs.div_euclid(86_400);
+    let time = secs.rem_euclid(86_400);
+
+    // see http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    let mut s = format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        time / 3600,
+        time % 3600 / 60,
+        time % 60
+    );
+
+    if nanos > 0 {
+        s.push_str(format!(".{:09}", nanos).trim_end_matches('0'));
+    }
+    s.push('Z');
+
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{binary, rfc3339, stream, type_name};
+    use crate::json::{Annotation, JsonTokenTag};
+
+    #[test]
+    fn test_rfc3339() {
+        assert_eq!(rfc3339(0, 0), "1970-01-01T00:00:00Z");
+        assert_eq!(rfc3339(1_582_934_400, 0), "2020-02-29T00:00:00Z");
+        assert_eq!(rfc3339(951_868_799, 500_000_000), "2000-02-29T23:59:59.5Z");
+        assert_eq!(rfc3339(-1, 1), "1969-12-31T23:59:59.000000001Z");
+        assert_eq!(rfc3339(253_402_300_799, 0), "9999-12-31T23:59:59Z");
+    }
+
+    #[test]
+    fn test_binary() {
+        let mut annotations = vec![];
+
+        assert_eq!(binary(b"hi", "/a", &mut annotations), json!("aGk="));
+        assert_eq!(
+            binary(&[0; 20], "/b", &mut annotations),
+            json!("AAAAAAAAAAAAAAAAAAAAAAAAAAA=")
+        );
+
+        assert_eq!(
+            annotations
+                .iter()
+                .map(|a| (a.pointer.as_str(), a.tag, a.text.as_deref().unwrap()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("/a", Some(JsonTokenTag::Binary), "<6869 2 bytes>"),
+                (
+                    "/b",
+                    Some(JsonTokenTag::Binary),
+                    "<00000000000000000000000000000000… 20 bytes>"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_stream() {
+        let annotated = |pointer: &str| {
+            let mut annotations = vec![];
+            type_name(pointer, "T", &mut annotations);
+            annotations
+        };
+        let pointers = |annotations: Vec<Annotation>| {
+            annotations
+                .into_iter()
+                .map(|a| a.pointer)
+                .collect::<Vec<_>>()
+        };
+
+        let (values, annotations) = stream(vec![(json!(1), annotated(""))]);
+        assert_eq!(values, vec![json!(1)]);
+        assert_eq!(pointers(annotations), vec![""]);
+
+        let (values, annotations) = stream(vec![
+            (json!({"a": 1}), annotated("/a")),
+            (json!(2), annotated("")),
+        ]);
+        assert_eq!(values, vec![json!({"a": 1}), json!(2)]);
+        assert_eq!(pointers(annotations), vec!["/0/a", "/1"]);
+    }
+}
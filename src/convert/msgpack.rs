@@ -0,0 +1,181 @@
+//! MessagePack streams converted to JSON values, one per top level value.
+
+use std::convert::TryInto;
+
+use serde_json::{Map, Value};
+
+use super::{binary, child, datetime, float, key_string, rfc3339, stream, type_name};
+use crate::json::Annotation;
+
+pub use rmpv::decode::Error;
+
+/// Type of the extension for timestamps.
+const TIMESTAMP: i8 = -1;
+
+/// Parse all the values of the given MessagePack stream. The binary data and
+/// the extensions are annotated with their preview and with their type.
+pub fn documents(input: &[u8]) -> Result<(Vec<Value>, Vec<Annotation>), Error> {
+    let mut rd = input;
+    let mut values = vec![];
+
+    while !rd.is_empty() {
+        let value = rmpv::decode::read_value(&mut rd)?;
+
+        let mut annotations = vec![];
+        let value = convert(value, "", &mut annotations);
+        values.push((value, annotations));
+    }
+
+    Ok(stream(values))
+}
+
+fn convert(value: rmpv::Value, path: &str, annotations: &mut Vec<Annotation>) -> Value {
+    match value {
+        rmpv::Value::Nil => Value::Null,
+        rmpv::Value::Boolean(b) => Value::Bool(b),
+        rmpv::Value::Integer(i) => match (i.as_i64(), i.as_u64()) {
+            (Some(i), _) => Value::from(i),
+            (_, Some(u)) => Value::from(u),
+            _ => Value::Null,
+        },
+        rmpv::Value::F32(f) => float(f.into()),
+        rmpv::Value::F64(f) => float(f),
+
+        // a string isn't necessarily valid UTF-8
+        rmpv::Value::String(s) => match s.as_str() {
+            Some(s) => Value::String(s.to_string()),
+            None => {
+                type_name(path, "str", annotations);
+                binary(s.as_bytes(), path, annotations)
+            }
+        },
+        rmpv::Value::Binary(bytes) => binary(&bytes, path, annotations),
+        rmpv::Value::Ext(TIMESTAMP, data) => match timestamp(&data) {
+            Some((secs, nanos)) => {
+                type_name(path, "Timestamp", annotations);
+                datetime(path, annotations);
+                Value::String(rfc3339(secs, nanos))
+            }
+            None => {
+                type_name(path, format!("ext({})", TIMESTAMP), annotations);
+                binary(&data, path, annotations)
+            }
+        },
+        rmpv::Value::Ext(ty, data) => {
+            type_name(path, format!("ext({})", ty), annotations);
+            binary(&data, path, annotations)
+        }
+        rmpv::Value::Array(items) => Value::Array(
+            items
+                .into_iter()
+                .enumerate()
+                .map(|(i, v)| convert(v, &child(path, &i.to_string()), annotations))
+                .collect(),
+        ),
+        rmpv::Value::Map(entries) => Value::Object(
+            entries
+                .into_iter()
+                .map(|(k, v)| {
+                    // the keys can be anything, but only strings can be shown
+                    let k = key_string(convert(k, "", &mut vec![]));
+                    let v = convert(v, &child(path, &k), annotations);
+                    (k, v)
+                })
+                .collect::<Map<_, _>>(),
+        ),
+    }
+}
+
+/// Decode the seconds and nanoseconds of a timestamp, in any of its 32, 64 or
+/// 96 bits formats.
+fn timestamp(data: &[u8]) -> Option<(i64, u32)> {
+    match data.len() {
+        4 => Some((u32::from_be_bytes(data.try_into().ok()?).into(), 0)),
+        8 => {
+            let n = u64::from_be_bytes(data.try_into().ok()?);
+            Some(((n & 0x3_ffff_ffff) as i64, (n >> 34) as u32))
+        }
+        12 => Some((
+            i64::from_be_bytes(data[4..].try_into().ok()?),
+            u32::from_be_bytes(data[..4].try_into().ok()?),
+        )),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{documents, timestamp};
+    use crate::json::JsonTokenTag;
+
+    #[test]
+    fn test_documents() {
+        let input = [
+            // {"name": "jv", "tags": [1, -1, 1.5, nil, true], 7: bin "hi"}
+            &b"\x83\xa4name\xa2jv\xa4tags\x95\x01\xff\xcb\x3f\xf8\0\0\0\0\0\0\xc0\xc3"[..],
+            b"\x07\xc4\x02hi",
+            // a timestamp and a custom extension
+            b"\x92\xd6\xff\x00\x00\x00\x3c\xd4\x05\x2a",
+            // an invalid UTF-8 string
+            b"\xa1\xff",
+        ]
+        .concat();
+
+        let (values, annotations) = documents(&input).unwrap();
+
+        assert_eq!(
+            values,
+            vec![
+                json!({"name": "jv", "tags": [1, -1, 1.5, null, true], "7": "aGk="}),
+                json!(["1970-01-01T00:01:00Z", "Kg=="]),
+                json!("/w=="),
+            ]
+        );
+        assert_eq!(
+            annotations
+                .iter()
+                .map(|a| (
+                    a.pointer.as_str(),
+                    a.tag,
+                    a.text.as_deref(),
+                    a.type_name.as_deref()
+                ))
+                .collect::<Vec<_>>(),
+            vec![
+                (
+                    "/0/7",
+                    Some(JsonTokenTag::Binary),
+                    Some("<6869 2 bytes>"),
+                    None
+                ),
+                ("/1/0", None, None, Some("Timestamp")),
+                ("/1/0", Some(JsonTokenTag::DateTime), None, None),
+                ("/1/1", None, None, Some("ext(5)")),
+                (
+                    "/1/1",
+                    Some(JsonTokenTag::Binary),
+                    Some("<2a 1 bytes>"),
+                    None
+                ),
+                ("/2", None, None, Some("str")),
+                ("/2", Some(JsonTokenTag::Binary), Some("<ff 1 bytes>"), None),
+            ]
+        );
+
+        assert!(documents(b"").unwrap().0.is_empty());
+        assert!(documents(b"\x92\x01").is_err());
+    }
+
+    #[test]
+    fn test_timestamp() {
+        assert_eq!(timestamp(&[0, 0, 0, 1]), Some((1, 0)));
+        assert_eq!(timestamp(&[0, 0, 0, 4, 0, 0, 0, 1]), Some((1, 1)));
+        assert_eq!(
+            timestamp(&[0, 0, 0, 2, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]),
+            Some((-1, 2))
+        );
+        assert_eq!(timestamp(&[0]), None);
+    }
+}
@@ -0,0 +1,122 @@
+//! TOML documents converted to JSON values.
+
+use serde_json::{Map, Value};
+
+use super::{child, datetime, float};
+use crate::json::Annotation;
+
+pub use toml::de::Error;
+
+/// Parse the given TOML document. The datetimes become strings, so they're
+/// annotated to tell them apart from the other strings.
+pub fn document(input: &str) -> Result<(Value, Vec<Annotation>), Error> {
+    let table = input.parse::<toml::Table>()?;
+
+    let mut annotations = vec![];
+    let value = convert(toml::Value::Table(table), "", &mut annotations);
+
+    Ok((value, annotations))
+}
+
+fn convert(value: toml::Value, path: &str, annotations: &mut Vec<Annotation>) -> Value {
+    match value {
+        toml::Value::String(s) => Value::String(s),
+        toml::Value::Integer(i) => Value::from(i),
+        toml::Value::Boolean(b) => Value::Bool(b),
+        toml::Value::Float(f) => float(f),
+        toml::Value::Datetime(dt) => {
+            datetime(path, annotations);
+            Value::String(dt.to_string())
+        }
+        toml::Value::Array(items) => Value::Array(
+            items
+                .into_iter()
+                .enumerate()
+                .map(|(i, v)| convert(v, &child(path, &i.to_string()), annotations))
+                .collect(),
+        ),
+        toml::Value::Table(table) => Value::Object(
+            table
+                .into_iter()
+                .map(|(k, v)| {
+                    let v = convert(v, &child(path, &k), annotations);
+                    (k, v)
+                })
+                .collect::<Map<_, _>>(),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::document;
+    use crate::json::JsonTokenTag;
+
+    #[test]
+    fn test_document() {
+        let input = r#"
+# the manifest
+[package]
+name = "jv"
+version = "0.1.0"
+edition = 2018
+published = 1979-05-27T07:32:00Z
+
+[dependencies]
+serde_json = { version = "1.0", features = ["preserve_order"] }
+
+[[bin]]
+name = "jv"
+path = "src/main.rs"
+
+[[bin]]
+"a/b" = [1.5, inf, 07:32:00]
+"#;
+
+        let (value, annotations) = document(input).unwrap();
+
+        assert_eq!(
+            value,
+            json!({
+                "package": {
+                    "name": "jv",
+                    "version": "0.1.0",
+                    "edition": 2018,
+                    "published": "1979-05-27T07:32:00Z",
+                },
+                "dependencies": {
+                    "serde_json": {"version": "1.0", "features": ["preserve_order"]},
+                },
+                "bin": [
+                    {"name": "jv", "path": "src/main.rs"},
+                    {"a/b": [1.5, "inf", "07:32:00"]},
+                ],
+            })
+        );
+        assert_eq!(
+            annotations
+                .iter()
+                .map(|a| (a.pointer.as_str(), a.tag))
+                .collect::<Vec<_>>(),
+            vec![
+                ("/package/published", Some(JsonTokenTag::DateTime)),
+                ("/bin/1/a~1b/2", Some(JsonTokenTag::DateTime)),
+            ]
+        );
+
+        // the keys are in the order of the document
+        assert_eq!(
+            value["package"]
+                .as_object()
+                .unwrap()
+                .keys()
+                .collect::<Vec<_>>(),
+            vec!["name", "version", "edition", "published"]
+        );
+
+        assert!(document("[package\nname = 1").is_err());
+        assert!(document("a = 1\na = 2").is_err());
+    }
+}
@@ -0,0 +1,449 @@
+//! YAML streams converted to JSON values, one per document.
+
+use std::collections::HashMap;
+
+use serde_json::{Map, Number, Value};
+use yaml_rust2::parser::{Event, Parser, Tag};
+use yaml_rust2::scanner::{Marker, ScanError, TScalarStyle};
+use yaml_rust2::Yaml;
+
+use super::{child, key_string, stream};
+use crate::json::Annotation;
+
+/// Key of the mappings whose entries are merged into the mapping it's in.
+const MERGE_KEY: &str = "<<";
+
+/// Maximum number of nodes copied by the aliases of a stream, so that a few
+/// nested aliases can't expand into billions of nodes.
+const MAX_ALIAS_NODES: usize = 1_000_000;
+
+/// Parse all the documents of the given YAML stream. The aliases are copies of
+/// the node of their anchor, and the mappings merged with `<<` are added to
+/// the mapping they're in. The copies are annotated with the reference of
+/// their anchor, like `#/defaults`, or like `#/<document>/defaults` when the
+/// stream has more than one document. The anchors of keys can't be referenced
+/// so their copies aren't annotated. Fails when the aliases copy more than
+/// `MAX_ALIAS_NODES` nodes.
+pub fn documents(input: &str) -> Result<(Vec<Value>, Vec<Annotation>), ScanError> {
+    let mut parser = Parser::new_from_str(input);
+    let mut builder = Builder::default();
+
+    loop {
+        match parser.next_token()? {
+            (Event::StreamEnd, _) => break,
+            (ev, mark) => builder.push(ev, mark)?,
+        }
+    }
+
+    Ok(builder.finish())
+}
+
+/// A collection whose items are being parsed.
+#[derive(Debug)]
+enum Node {
+    Sequence(Vec<Value>),
+
+    // the mapping, the key of the value being parsed, if any, and the values
+    // to merge into the mapping along with the pointer of their anchor
+    Mapping(
+        Map<String, Value>,
+        Option<String>,
+        Vec<(Value, Option<String>)>,
+    ),
+}
+
+#[derive(Debug, Default)]
+struct Builder {
+    docs: Vec<(Value, Vec<(String, String)>)>,
+    root: Option<Value>,
+
+    // collections being parsed along with their pointer and anchor id
+    stack: Vec<(Node, String, usize)>,
+
+    // pointer and value of the nodes with an anchor by anchor id, keys have
+    // no pointer
+    anchors: HashMap<usize, (Option<String>, Value)>,
+
+    // number of nodes copied by the aliases so far
+    copied: usize,
+
+    // pointers of the copies of the current document and of their anchor
+    aliases: Vec<(String, String)>,
+}
+
+impl Builder {
+    fn push(&mut self, ev: Event, mark: Marker) -> Result<(), ScanError> {
+        match ev {
+            Event::SequenceStart(anchor, _) => self.start(Node::Sequence(vec![]), anchor),
+            Event::MappingStart(anchor, _) => {
+                self.start(Node::Mapping(Map::new(), None, vec![]), anchor)
+            }
+            Event::SequenceEnd | Event::MappingEnd => {
+                let (node, pointer, anchor) = self.stack.pop().unwrap();
+                let value = match node {
+                    Node::Sequence(items) => Value::Array(items),
+                    Node::Mapping(mut map, _, merges) => {
+                        for (value, alias) in merges {
+                            self.merge(&mut map, &pointer, value, alias.as_deref());
+                        }
+                        Value::Object(map)
+                    }
+                };
+
+                if let Some((_, v)) = self.anchors.get_mut(&anchor) {
+                    *v = value.clone();
+                }
+
+                self.insert(value, None);
+            }
+            Event::Scalar(v, style, anchor, tag) => {
+                let value = scalar(v, style, tag);
+
+                if anchor > 0 {
+                    let pointer = self.next_pointer();
+                    self.anchors.insert(anchor, (pointer, value.clone()));
+                }
+
+                self.insert(value, None);
+            }
+            Event::Alias(anchor) => {
+                let (alias, value) = self.anchors.get(&anchor).cloned().unwrap_or_default();
+
+                self.copied += nodes(&value);
+                if self.copied > MAX_ALIAS_NODES {
+                    return Err(ScanError::new(mark, "too many nodes copied by aliases"));
+                }
+
+                self.insert(value, alias);
+            }
+            Event::DocumentEnd => {
+                let root = self.root.take().unwrap_or(Value::Null);
+                self.docs.push((root, std::mem::take(&mut self.aliases)));
+            }
+            Event::Nothing | Event::StreamStart | Event::StreamEnd | Event::DocumentStart => {}
+        }
+
+        Ok(())
+    }
+
+    fn start(&mut self, node: Node, anchor: usize) {
+        let pointer = self.next_pointer();
+
+        // the anchor is known before the node ends since it can be used inside
+        // the node itself
+        if anchor > 0 {
+            self.anchors.insert(anchor, (pointer.clone(), Value::Null));
+        }
+
+        self.stack.push((node, pointer.unwrap_or_default(), anchor));
+    }
+
+    /// The pointer of the next node or `None` if it's the key of a mapping.
+    fn next_pointer(&self) -> Option<String> {
+        match self.stack.last() {
+            None => Some(String::new()),
+            Some((Node::Sequence(items), p, _)) => Some(format!("{}/{}", p, items.len())),
+            Some((Node::Mapping(_, Some(k), _), p, _)) => Some(child(p, k)),
+            Some((Node::Mapping(_, None, _), _, _)) => None,
+        }
+    }
+
+    /// Insert the next node, which is a copy of the node at the given pointer
+    /// if it's an alias.
+    fn insert(&mut self, value: Value, alias: Option<String>) {
+        let pointer = self.next_pointer();
+
+        match self.stack.last_mut() {
+            None => self.root = Some(value),
+            Some((Node::Sequence(items), _, _)) => items.push(value),
+
+            // a key must be a string, a copy of another node won't do
+            Some((Node::Mapping(_, key @ None, _), _, _)) => {
+                *key = Some(key_string(value));
+                return;
+            }
+            Some((Node::Mapping(_, key, merges), _, _)) if key.as_deref() == Some(MERGE_KEY) => {
+                *key = None;
+                merges.push((value, alias));
+                return;
+            }
+            Some((Node::Mapping(map, key, _), _, _)) => {
+                map.insert(key.take().unwrap_or_default(), value);
+            }
+        }
+
+        if let (Some(pointer), Some(alias)) = (pointer, alias) {
+            self.aliases.push((pointer, alias));
+        }
+    }
+
+    /// Add the entries of the merged mapping, or of the sequence of mappings,
+    /// that the mapping at the given pointer doesn't have. The first mappings
+    /// of a sequence win over the following ones.
+    fn merge(
+        &mut self,
+        map: &mut Map<String, Value>,
+        pointer: &str,
+        value: Value,
+        alias: Option<&str>,
+    ) {
+        match value {
+            Value::Object(merged) => {
+                for (k, v) in merged {
+                    if map.contains_key(&k) {
+                        continue;
+                    }
+
+                    if let Some(alias) = alias {
+                        self.aliases.push((child(pointer, &k), child(alias, &k)));
+                    }
+                    map.insert(k, v);
+                }
+            }
+            Value::Array(items) => {
+                for item in items {
+                    self.merge(map, pointer, item, None);
+                }
+            }
+
+            // only mappings can be merged, anything else is a regular value
+            value => {
+                map.entry(MERGE_KEY.to_string()).or_insert(value);
+            }
+        }
+    }
+
+    fn finish(self) -> (Vec<Value>, Vec<Annotation>) {
+        let many = self.docs.len() > 1;
+
+        let docs = self
+            .docs
+            .into_iter()
+            .enumerate()
+            .map(|(i, (value, aliases))| {
+                let annotations = aliases
+                    .into_iter()
+                    .map(|(pointer, alias)| Annotation {
+                        pointer,
+                        alias: Some(if many {
+                            format!("#/{}{}", i, alias)
+                        } else {
+                            format!("#{}", alias)
+                        }),
+                        ..Annotation::default()
+                    })
+                    .collect();
+
+                (value, annotations)
+            })
+            .collect();
+
+        stream(docs)
+    }
+}
+
+/// Count the nodes of the given value, itself included.
+fn nodes(value: &Value) -> usize {
+    match value {
+        Value::Array(items) => 1 + items.iter().map(nodes).sum::<usize>(),
+        Value::Object(map) => 1 + map.values().map(nodes).sum::<usize>(),
+        _ => 1,
+    }
+}
+
+/// Resolve the type of a scalar like YAML does, only the plain scalars can be
+/// something other than strings. The values that JSON can't represent, like
+/// `.inf`, are kept as strings.
+fn scalar(v: String, style: TScalarStyle, tag: Option<Tag>) -> Value {
+    if style != TScalarStyle::Plain {
+        return Value::String(v);
+    }
+
+    // the application specific tags, like !Ref in CloudFormation templates, are
+    // ignored
+    if let Some(tag) = tag {
+        if tag.handle == "tag:yaml.org,2002:" && tag.suffix == "str" {
+            return Value::String(v);
+        }
+    }
+
+    match Yaml::from_str(&v) {
+        Yaml::Null => Value::Null,
+        Yaml::Boolean(b) => Value::Bool(b),
+        Yaml::Integer(i) => Value::from(i),
+        Yaml::Real(r) => match r.parse().ok().and_then(Number::from_f64) {
+            Some(n) => Value::Number(n),
+            None => Value::String(v),
+        },
+        _ => Value::String(v),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::documents;
+    use crate::json::Annotation;
+
+    fn aliases(annotations: &[Annotation]) -> Vec<(&str, &str)> {
+        annotations
+            .iter()
+            .map(|a| (a.pointer.as_str(), a.alias.as_deref().unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn test_documents() {
+        let input = r#"
+name: jv
+version: 0.1
+tags: [json, "yaml", 'viewer']
+private: false
+license: ~
+build:
+  - cargo build
+  - |
+    cargo test
+1: one
+"quoted": .inf
+"#;
+
+        assert_eq!(
+            documents(input).unwrap(),
+            (
+                vec![json!({
+                    "name": "jv",
+                    "version": 0.1,
+                    "tags": ["json", "yaml", "viewer"],
+                    "private": false,
+                    "license": null,
+                    "build": ["cargo build", "cargo test\n"],
+                    "1": "one",
+                    "quoted": ".inf",
+                })],
+                vec![]
+            )
+        );
+
+        assert!(documents("").unwrap().0.is_empty());
+        assert_eq!(documents("!!str 42").unwrap().0, vec![json!("42")]);
+        assert!(documents("a: [1, 2").is_err());
+    }
+
+    #[test]
+    fn test_aliases() {
+        let input = r#"
+defaults: &defaults
+  image: rust
+  tags: &tags [a/b, c]
+build:
+  <<: *defaults
+  image: alpine
+  cache: *tags
+*tags : key
+"#;
+
+        let (values, annotations) = documents(input).unwrap();
+
+        assert_eq!(
+            values,
+            vec![json!({
+                "defaults": {"image": "rust", "tags": ["a/b", "c"]},
+                "build": {"image": "alpine", "cache": ["a/b", "c"], "tags": ["a/b", "c"]},
+                "[\"a/b\",\"c\"]": "key",
+            })]
+        );
+        assert_eq!(
+            aliases(&annotations),
+            vec![
+                ("/build/cache", "#/defaults/tags"),
+                ("/build/tags", "#/defaults/tags"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_key_anchors() {
+        let input = r#"
+&k key: v
+? &m [a]
+: w
+other: *k
+list: *m
+"#;
+
+        let (values, annotations) = documents(input).unwrap();
+
+        assert_eq!(
+            values,
+            vec![json!({"key": "v", "[\"a\"]": "w", "other": "key", "list": ["a"]})]
+        );
+        assert!(annotations.is_empty());
+    }
+
+    #[test]
+    fn test_alias_limit() {
+        let mut input = String::from("a0: &a0 [x, x, x, x, x, x, x, x, x, x]\n");
+        for i in 1..10 {
+            let aliases = vec![format!("*a{}", i - 1); 10].join(", ");
+            input.push_str(&format!("a{}: &a{} [{}]\n", i, i, aliases));
+        }
+
+        let err = documents(&input).unwrap_err();
+
+        assert_eq!(err.info(), "too many nodes copied by aliases");
+        assert_eq!(err.marker().line(), 6);
+        assert!(documents("a: &a [x]\nb: [*a, *a]").is_ok());
+    }
+
+    #[test]
+    fn test_merge_sequence() {
+        let input = r#"
+a: &a {x: 1, y: 1}
+b: &b {y: 2, z: 2}
+c:
+  <<: [*a, *b]
+  z: 3
+"#;
+
+        let (values, _) = documents(input).unwrap();
+
+        assert_eq!(values[0]["c"], json!({"x": 1, "y": 1, "z": 3}));
+        assert_eq!(
+            documents("a: {<<: 1}").unwrap().0,
+            vec![json!({"a": {"<<": 1}})]
+        );
+    }
+
+    #[test]
+    fn test_many_documents() {
+        let input = r#"
+kind: Service
+spec: &spec
+  port: 80
+---
+---
+kind: Deployment
+spec: &spec
+  replicas: 1
+template: *spec
+"#;
+
+        let (values, annotations) = documents(input).unwrap();
+
+        assert_eq!(
+            values,
+            vec![
+                json!({"kind": "Service", "spec": {"port": 80}}),
+                json!(null),
+                json!({
+                    "kind": "Deployment",
+                    "spec": {"replicas": 1},
+                    "template": {"replicas": 1},
+                }),
+            ]
+        );
+        assert_eq!(aliases(&annotations), vec![("/2/template", "#/2/spec")]);
+    }
+}
@@ -0,0 +1,56 @@
+use std::io;
+use std::sync::mpsc;
+use std::thread;
+
+use signal_hook::consts::SIGWINCH;
+use signal_hook::iterator::Signals;
+
+use termion::event::{self, Key, MouseEvent};
+use termion::input::TermRead;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    Key(Key),
+
+    /// A mouse button has been pressed or released, the mouse reporting must
+    /// be enabled for the terminal to send them.
+    Mouse(MouseEvent),
+
+    /// The terminal has been resized, its new size must be queried.
+    Resize,
+}
+
+/// Start listening for the keys pressed and the mouse events in the terminal
+/// and for the terminal being resized. The keys are read from the terminal
+/// directly because the input might have been read from stdin.
+pub fn events() -> io::Result<mpsc::Receiver<io::Result<Event>>> {
+    let (tx, rx) = mpsc::channel();
+
+    let tty = termion::get_tty()?;
+    let keys_tx = tx.clone();
+    thread::spawn(move || {
+        for ev in tty.events() {
+            let ev = match ev {
+                Ok(event::Event::Key(key)) => Ok(Event::Key(key)),
+                Ok(event::Event::Mouse(mouse)) => Ok(Event::Mouse(mouse)),
+                Ok(event::Event::Unsupported(_)) => continue,
+                Err(err) => Err(err),
+            };
+
+            if keys_tx.send(ev).is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut signals = Signals::new([SIGWINCH])?;
+    thread::spawn(move || {
+        for _ in signals.forever() {
+            if tx.send(Ok(Event::Resize)).is_err() {
+                break;
+            }
+        }
+    });
+
+    Ok(rx)
+}
@@ -0,0 +1,479 @@
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+
+use serde_json::Value;
+
+use crate::convert::{bson, cbor, json5, msgpack, toml, yaml};
+use crate::json::{Annotation, Comment};
+
+/// The formats of the documents `jv` knows how to view.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Json,
+
+    /// Newline delimited JSON, also known as JSON Lines, where each line is a
+    /// separate JSON value.
+    Ndjson,
+    Yaml,
+    Toml,
+    MessagePack,
+    Cbor,
+    Bson,
+    Text,
+}
+
+/// An input parsed to detect its format, it doesn't have to be parsed again to
+/// be viewed.
+#[derive(Debug)]
+pub enum Parsed {
+    /// A JSON document along with its comments, if it's JSON5.
+    Json(Value, Vec<Comment>),
+
+    /// The documents converted from another format along with their
+    /// annotations.
+    Documents(Vec<Value>, Vec<Annotation>),
+}
+
+impl Format {
+    /// The names of all the formats as accepted by `FromStr`.
+    pub fn variants() -> &'static [&'static str] {
+        &[
+            "json", "ndjson", "yaml", "toml", "msgpack", "cbor", "bson", "text",
+        ]
+    }
+
+    /// Detect the format of the given input by sniffing its content. Text is
+    /// returned only if the input doesn't look like any other format.
+    pub fn detect(input: &[u8]) -> Format {
+        Format::detect_and_parse(input).0
+    }
+
+    /// Detect the format of the given input like `detect` along with the input
+    /// parsed, unless it didn't have to be parsed to tell its format, like
+    /// NDJSON whose first line is enough.
+    pub fn detect_and_parse(input: &[u8]) -> (Format, Option<Parsed>) {
+        // the binary formats are checked first, their first bytes are never
+        // the start of a text
+        if let Some((values, annotations)) = parse_bson(input) {
+            return (Format::Bson, Some(Parsed::Documents(values, annotations)));
+        }
+
+        // the maps and arrays of MessagePack are arrays in CBOR too, e.g. 0x90
+        // followed by 16 integers is an array of them in CBOR but 17 values in
+        // MessagePack, so the format that makes fewer values out of the input
+        // wins
+        let binary = match (parse_msgpack(input), parse_cbor(input)) {
+            (Some(msgpack), Some(cbor)) if cbor.0.len() < msgpack.0.len() => {
+                Some((Format::Cbor, cbor))
+            }
+            (Some(msgpack), _) => Some((Format::MessagePack, msgpack)),
+            (None, Some(cbor)) => Some((Format::Cbor, cbor)),
+            (None, None) => None,
+        };
+        if let Some((format, (values, annotations))) = binary {
+            return (format, Some(Parsed::Documents(values, annotations)));
+        }
+
+        let input = input.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(input);
+
+        let first = input.iter().find(|b| !b.is_ascii_whitespace());
+
+        let json = match first {
+            Some(b'{') | Some(b'[') if is_ndjson(input) => return (Format::Ndjson, None),
+
+            // plenty of text files start with a bracket too, like the tables of
+            // TOML, e.g. "[package]", or logs, e.g. "[INFO] started", so make
+            // sure the whole input is actually JSON.
+            Some(b'{') | Some(b'[') => parse_json(input).or_else(|| parse_json5(input)),
+
+            // a document made of a single scalar is still valid JSON, but
+            // plenty of text files start with a quote, a number or a word like
+            // "true" so make sure the whole input is actually JSON.
+            Some(b'"') | Some(b'-') | Some(b'0'..=b'9') | Some(b't') | Some(b'f') | Some(b'n') => {
+                parse_json(input)
+            }
+
+            // JSON with comments, like the settings of many editors, often
+            // starts with one
+            Some(b'/') => parse_json5(input),
+
+            _ => None,
+        };
+        if let Some((value, comments)) = json {
+            return (Format::Json, Some(Parsed::Json(value, comments)));
+        }
+
+        if let Some((value, annotations)) = parse_toml(input) {
+            return (
+                Format::Toml,
+                Some(Parsed::Documents(vec![value], annotations)),
+            );
+        }
+
+        // YAML is a superset of JSON so it's checked only if the input isn't
+        // JSON
+        if let Some((values, annotations)) = parse_yaml(input) {
+            return (Format::Yaml, Some(Parsed::Documents(values, annotations)));
+        }
+
+        (Format::Text, None)
+    }
+}
+
+/// Parse the input if it's a sequence of BSON documents, that is the lengths
+/// at the start of the documents add up to the size of the input, each
+/// document ends with a nul byte, and they're valid BSON.
+fn parse_bson(input: &[u8]) -> Option<(Vec<Value>, Vec<Annotation>)> {
+    let mut rest = input;
+
+    while !rest.is_empty() {
+        let len = match rest.get(..4) {
+            Some(len) => i32::from_le_bytes([len[0], len[1], len[2], len[3]]),
+            None => return None,
+        };
+
+        // the smallest document is the length and the nul byte
+        let len = match usize::try_from(len) {
+            Ok(len) if len >= 5 && len <= rest.len() => len,
+            _ => return None,
+        };
+        if rest[len - 1] != 0 {
+            return None;
+        }
+
+        rest = &rest[len..];
+    }
+
+    if input.is_empty() {
+        return None;
+    }
+
+    bson::documents(input).ok()
+}
+
+/// Parse the input if it's a stream of MessagePack values starting with a map
+/// or an array, which is how most documents start, and it's valid MessagePack.
+fn parse_msgpack(input: &[u8]) -> Option<(Vec<Value>, Vec<Annotation>)> {
+    if !matches!(input.first(), Some(0x80..=0x9f) | Some(0xdc..=0xdf)) {
+        return None;
+    }
+
+    msgpack::documents(input).ok()
+}
+
+/// Parse the input if it's a sequence of CBOR items starting with the self
+/// described tag, or with an array or a map, and it's valid CBOR.
+fn parse_cbor(input: &[u8]) -> Option<(Vec<Value>, Vec<Annotation>)> {
+    let starts_like_cbor =
+        input.starts_with(b"\xd9\xd9\xf7") || matches!(input.first(), Some(0x80..=0xbf));
+    if !starts_like_cbor {
+        return None;
+    }
+
+    cbor::documents(input).ok()
+}
+
+/// Whether the input looks like newline delimited JSON, that is the first non
+/// empty line is a complete JSON value and it's followed by other values. A
+/// pretty printed document never has a complete value on its first line.
+fn is_ndjson(input: &[u8]) -> bool {
+    let mut lines = input
+        .split(|b| *b == b'\n')
+        .filter(|l| l.iter().any(|b| !b.is_ascii_whitespace()));
+
+    match lines.next() {
+        Some(first) => {
+            serde_json::from_slice::<serde_json::Value>(first).is_ok() && lines.next().is_some()
+        }
+        None => false,
+    }
+}
+
+/// Parse the input if it's a JSON document, it has no comments.
+fn parse_json(input: &[u8]) -> Option<(Value, Vec<Comment>)> {
+    let value = serde_json::from_slice(input).ok()?;
+
+    Some((value, vec![]))
+}
+
+/// Parse the input if it's a JSON5 document, or a JSON document with comments.
+fn parse_json5(input: &[u8]) -> Option<(Value, Vec<Comment>)> {
+    json5::document(std::str::from_utf8(input).ok()?).ok()
+}
+
+/// Parse the input if it looks like TOML, that is the first line that is not a
+/// comment is either a table header or a key value pair, and it's valid TOML.
+fn parse_toml(input: &[u8]) -> Option<(Value, Vec<Annotation>)> {
+    let input = std::str::from_utf8(input).ok()?;
+
+    let first = input
+        .lines()
+        .map(str::trim)
+        .find(|l| !l.is_empty() && !l.starts_with('#'));
+
+    let looks_like_toml = match first {
+        Some(l) if l.starts_with('[') => l.split('#').next().unwrap_or(l).trim_end().ends_with(']'),
+        Some(l) => match l.split_once('=') {
+            Some((key, _)) => {
+                let key = key.trim();
+
+                key.starts_with('"')
+                    || key.starts_with('\'')
+                    || (!key.is_empty()
+                        && key
+                            .chars()
+                            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.')))
+            }
+            None => false,
+        },
+        None => false,
+    };
+
+    if !looks_like_toml {
+        return None;
+    }
+
+    toml::document(input).ok()
+}
+
+/// Parse the input if it looks like YAML, that is it starts with a directive or a
+/// document marker or all the lines that are not indented are keys or items of
+/// a sequence. Plenty of text files have lines like "Note: something" so keys
+/// with spaces are not considered, unless they're quoted. The documents must
+/// be mappings or sequences too, like the front matter of a Markdown file is
+/// but its text isn't.
+fn parse_yaml(input: &[u8]) -> Option<(Vec<Value>, Vec<Annotation>)> {
+    let input = std::str::from_utf8(input).ok()?;
+
+    let mut lines = input
+        .lines()
+        .filter(|l| !l.trim().is_empty() && !l.starts_with('#'))
+        .peekable();
+
+    let looks_like_yaml = match lines.peek() {
+        Some(first) if first.starts_with("---") || first.starts_with("%YAML") => true,
+        Some(_) => lines
+            .filter(|l| !l.starts_with(char::is_whitespace))
+            .all(|l| l == "-" || l.starts_with("- ") || l.starts_with("---") || is_yaml_key(l)),
+        None => false,
+    };
+
+    if !looks_like_yaml {
+        return None;
+    }
+
+    yaml::documents(input).ok().filter(|(docs, _)| {
+        docs.iter()
+            .all(|d| d.is_object() || d.is_array() || d.is_null())
+    })
+}
+
+/// Whether the line starts with a key of a mapping followed by ":".
+fn is_yaml_key(line: &str) -> bool {
+    let rest = match line.chars().next() {
+        Some(q @ '"') | Some(q @ '\'') => line[1..].find(q).map(|i| line[i + 2..].trim_start()),
+        _ => line
+            .find(':')
+            .filter(|&i| i > 0 && !line[..i].contains(char::is_whitespace))
+            .map(|i| &line[i..]),
+    };
+
+    match rest.and_then(|r| r.strip_prefix(':')) {
+        Some(r) => r.is_empty() || r.starts_with(char::is_whitespace),
+        None => false,
+    }
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "json" => Ok(Format::Json),
+            "ndjson" => Ok(Format::Ndjson),
+            "yaml" | "yml" => Ok(Format::Yaml),
+            "toml" => Ok(Format::Toml),
+            "msgpack" | "messagepack" => Ok(Format::MessagePack),
+            "cbor" => Ok(Format::Cbor),
+            "bson" => Ok(Format::Bson),
+            "text" => Ok(Format::Text),
+            _ => Err(format!("unknown format {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Format::Json => write!(f, "json"),
+            Format::Ndjson => write!(f, "ndjson"),
+            Format::Yaml => write!(f, "yaml"),
+            Format::Toml => write!(f, "toml"),
+            Format::MessagePack => write!(f, "msgpack"),
+            Format::Cbor => write!(f, "cbor"),
+            Format::Bson => write!(f, "bson"),
+            Format::Text => write!(f, "text"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{Format, Parsed};
+
+    #[test]
+    fn test_detect_json() {
+        assert_eq!(Format::detect(b"{}"), Format::Json);
+        assert_eq!(Format::detect(b"  \n\t[1, 2, 3]"), Format::Json);
+        assert_eq!(Format::detect(b"\xEF\xBB\xBF{\"a\": 1}"), Format::Json);
+
+        assert_eq!(Format::detect(b"42"), Format::Json);
+        assert_eq!(Format::detect(b"-3.14\n"), Format::Json);
+        assert_eq!(Format::detect(b"\"hello\""), Format::Json);
+        assert_eq!(Format::detect(b"null"), Format::Json);
+        assert_eq!(Format::detect(b" true "), Format::Json);
+    }
+
+    #[test]
+    fn test_detect_json5() {
+        assert_eq!(Format::detect(b"{a: 1, b: [2,],}"), Format::Json);
+        assert_eq!(
+            Format::detect(b"// settings\n{\"a\": 1 /* one */}"),
+            Format::Json
+        );
+        assert_eq!(Format::detect(b"/* license */ [1]"), Format::Json);
+
+        assert_eq!(Format::detect(b"// not json\nsome code"), Format::Text);
+    }
+
+    #[test]
+    fn test_detect_ndjson() {
+        assert_eq!(Format::detect(b"{\"a\": 1}\n{\"a\": 2}\n"), Format::Ndjson);
+        assert_eq!(Format::detect(b"[1]\r\n\r\n[2, 3]\r\n"), Format::Ndjson);
+        assert_eq!(Format::detect(b"{\"a\": 1}\n{\"a\": \n"), Format::Ndjson);
+
+        assert_eq!(Format::detect(b"{\"a\": 1}\n"), Format::Json);
+        assert_eq!(Format::detect(b"{\n    \"a\": 1\n}\n"), Format::Json);
+        assert_eq!(Format::detect(b"[\n1,\n2\n]"), Format::Json);
+    }
+
+    #[test]
+    fn test_detect_yaml() {
+        assert_eq!(Format::detect(b"name: jv\nversion: 1\n"), Format::Yaml);
+        assert_eq!(Format::detect(b"# comment\n- a\n- b: c\n"), Format::Yaml);
+        assert_eq!(Format::detect(b"---\nkind: Pod\n"), Format::Yaml);
+        assert_eq!(Format::detect(b"a: 1\n---\nb: 2\n"), Format::Yaml);
+        assert_eq!(
+            Format::detect(b"\"on\":\n  push:\n    branches: [main]\n"),
+            Format::Yaml
+        );
+
+        assert_eq!(Format::detect(b"---\n---\nkind: Pod\n"), Format::Yaml);
+
+        assert_eq!(
+            Format::detect(b"---\ntitle: Hello\n---\n# Heading\n\nSome text here."),
+            Format::Text
+        );
+        assert_eq!(Format::detect(b"---\njust some text\n"), Format::Text);
+        assert_eq!(Format::detect(b"Note: some text\nmore text"), Format::Text);
+        assert_eq!(Format::detect(b"Dear John: hello"), Format::Text);
+        assert_eq!(Format::detect(b"a: [1, 2"), Format::Text);
+        assert_eq!(Format::detect(b"see https://example.com"), Format::Text);
+    }
+
+    #[test]
+    fn test_detect_toml() {
+        assert_eq!(Format::detect(b"[package]\nname = \"jv\"\n"), Format::Toml);
+        assert_eq!(
+            Format::detect(b"[[bin]] # first\npath = 'a'\n"),
+            Format::Toml
+        );
+        assert_eq!(Format::detect(b"# config\nport = 80\n"), Format::Toml);
+        assert_eq!(Format::detect(b"a.b = [1, 2]\n"), Format::Toml);
+
+        assert_eq!(Format::detect(b"[\"a\"]"), Format::Json);
+        assert_eq!(Format::detect(b"[section]\nkey = value\n"), Format::Text);
+        assert_eq!(Format::detect(b"x = y + 1"), Format::Text);
+    }
+
+    #[test]
+    fn test_detect_binary() {
+        assert_eq!(Format::detect(b"\x81\xa4name\xa2jv"), Format::MessagePack);
+        assert_eq!(Format::detect(b"\x92\x01\x02\x90"), Format::MessagePack);
+        assert_eq!(Format::detect(b"\xa1\x64name\x62jv"), Format::Cbor);
+        assert_eq!(Format::detect(b"\xd9\xd9\xf7\x01"), Format::Cbor);
+        assert_eq!(
+            Format::detect(b"\x0c\0\0\0\x10a\0\x01\0\0\0\0\x05\0\0\0\0"),
+            Format::Bson
+        );
+
+        // valid in both formats, with fewer values in the detected one
+        let mut array = vec![0x90];
+        array.extend(0x00..=0x0f);
+        assert_eq!(Format::detect(&array), Format::Cbor);
+        assert_eq!(Format::detect(b"\x82\x01\x02\x03\x04"), Format::MessagePack);
+        assert_eq!(Format::detect(b"\x91\x01\x91\x02"), Format::MessagePack);
+
+        // truncated documents
+        assert_eq!(Format::detect(b"\x81\xa4name"), Format::Text);
+        assert_eq!(Format::detect(b"\xa1\x64name"), Format::Text);
+        assert_ne!(Format::detect(b"\x0c\0\0\0\x10a\0\x01\0\0\0"), Format::Bson);
+    }
+
+    #[test]
+    fn test_detect_text() {
+        assert_eq!(Format::detect(b""), Format::Text);
+        assert_eq!(Format::detect(b"   \n"), Format::Text);
+        assert_eq!(Format::detect(b"hello world"), Format::Text);
+        assert_eq!(Format::detect(b"42 is the answer"), Format::Text);
+        assert_eq!(Format::detect(b"\"quoted\" text"), Format::Text);
+        assert_eq!(Format::detect(b"[INFO] server started"), Format::Text);
+        assert_eq!(Format::detect(b"{{ .Values.name }}"), Format::Text);
+        assert_eq!(Format::detect(b"{\"a\": 1"), Format::Text);
+        assert_eq!(Format::detect(b"nothing to see here"), Format::Text);
+    }
+
+    #[test]
+    fn test_detect_and_parse() {
+        match Format::detect_and_parse(b"// c\n{\"a\": 1}") {
+            (Format::Json, Some(Parsed::Json(value, comments))) => {
+                assert_eq!(value, json!({"a": 1}));
+                assert_eq!(comments.len(), 1);
+            }
+            detected => panic!("unexpected {:?}", detected),
+        }
+
+        match Format::detect_and_parse(b"a: 1\n---\nb: 2\n") {
+            (Format::Yaml, Some(Parsed::Documents(values, _))) => {
+                assert_eq!(values, vec![json!({"a": 1}), json!({"b": 2})]);
+            }
+            detected => panic!("unexpected {:?}", detected),
+        }
+
+        assert!(matches!(
+            Format::detect_and_parse(b"{\"a\": 1}\n{\"a\": 2}\n"),
+            (Format::Ndjson, None)
+        ));
+        assert!(matches!(
+            Format::detect_and_parse(b"hello"),
+            (Format::Text, None)
+        ));
+    }
+
+    #[test]
+    fn test_parse_format() {
+        assert_eq!("json".parse(), Ok(Format::Json));
+        assert_eq!("JSON".parse(), Ok(Format::Json));
+        assert_eq!("ndjson".parse(), Ok(Format::Ndjson));
+        assert_eq!("yml".parse(), Ok(Format::Yaml));
+        assert_eq!("toml".parse(), Ok(Format::Toml));
+        assert_eq!("MessagePack".parse(), Ok(Format::MessagePack));
+        assert_eq!("cbor".parse(), Ok(Format::Cbor));
+        assert_eq!("bson".parse(), Ok(Format::Bson));
+        assert_eq!("text".parse(), Ok(Format::Text));
+        assert!("yolo".parse::<Format>().is_err());
+    }
+}
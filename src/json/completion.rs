@@ -0,0 +1,266 @@
+use serde_json::Value;
+
+use crate::json::jq::type_name;
+use crate::json::{pointer, KeyOrder};
+
+/// A possible next segment of a "/" separated path.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Completion {
+    /// Object key, escaped like in JSON Pointers, or array index.
+    pub segment: String,
+
+    /// Type of the value the segment refers to, like "object" or "string".
+    pub kind: &'static str,
+
+    /// Whether the value has children to complete further.
+    pub has_children: bool,
+}
+
+/// Find the completions of the last segment of the given "/" separated path,
+/// e.g. for `/items/0/na` all the keys of `/items/0` starting with "na". The
+/// keys are in the given order like the lines of the document.
+pub fn complete(value: &Value, path: &str, order: KeyOrder) -> Vec<Completion> {
+    let (parent, partial) = path.rsplit_once('/').unwrap_or(("", path));
+
+    let children = match value.pointer(parent) {
+        Some(Value::Object(obj)) => {
+            let mut children = obj.iter().collect::<Vec<_>>();
+            if order == KeyOrder::Sorted {
+                children.sort_by_key(|(k, _)| *k);
+            }
+
+            children
+                .into_iter()
+                .map(|(k, v)| (pointer::escape(k), v))
+                .collect::<Vec<_>>()
+        }
+        Some(Value::Array(arr)) => arr
+            .iter()
+            .enumerate()
+            .map(|(i, v)| (i.to_string(), v))
+            .collect(),
+        _ => vec![],
+    };
+
+    children
+        .into_iter()
+        .filter(|(segment, _)| segment.starts_with(partial))
+        .map(|(segment, v)| Completion {
+            segment,
+            kind: type_name(v),
+            has_children: match v {
+                Value::Object(obj) => !obj.is_empty(),
+                Value::Array(arr) => !arr.is_empty(),
+                _ => false,
+            },
+        })
+        .collect()
+}
+
+/// The longest prefix all the segments of the given completions start with.
+pub fn common_prefix(completions: &[Completion]) -> String {
+    let mut completions = completions.iter();
+
+    let mut prefix = match completions.next() {
+        Some(c) => c.segment.clone(),
+        None => return String::new(),
+    };
+
+    for c in completions {
+        let len = prefix
+            .char_indices()
+            .zip(c.segment.chars())
+            .find(|((_, a), b)| a != b)
+            .map_or_else(|| prefix.len().min(c.segment.len()), |((i, _), _)| i);
+
+        prefix.truncate(len);
+    }
+
+    prefix
+}
+
+/// Find the longest prefix of the given "/" separated path that exists in the
+/// value along with the key, if any, closest to the segment that follows it.
+/// The key is returned only if it's similar enough to the segment to likely be
+/// a typo, a third of the segment at most and less than the key itself.
+pub fn closest(value: &Value, path: &str) -> (String, Option<String>) {
+    let mut prefix = String::new();
+    let mut current = value;
+
+    for segment in path.split('/').skip(1) {
+        let child = match current {
+            Value::Object(obj) => obj.get(&pointer::unescape(segment)),
+            Value::Array(arr) => segment.parse::<usize>().ok().and_then(|i| arr.get(i)),
+            _ => None,
+        };
+
+        match child {
+            Some(child) => {
+                prefix.push('/');
+                prefix.push_str(segment);
+                current = child;
+            }
+            None => {
+                let obj = match current {
+                    Value::Object(obj) => obj,
+                    _ => return (prefix, None),
+                };
+
+                let max_distance = (segment.chars().count() / 3).max(1);
+                let key = obj
+                    .keys()
+                    .map(|k| pointer::escape(k))
+                    .map(|k| (edit_distance(segment, &k), k))
+                    .filter(|(d, k)| *d <= max_distance && *d < k.chars().count())
+                    .min_by_key(|(d, _)| *d)
+                    .map(|(_, k)| k);
+
+                return (prefix, key);
+            }
+        }
+    }
+
+    (prefix, None)
+}
+
+/// Edit distance between the two strings counted in chars. Swapping two chars
+/// next to each other is a single edit, like in "nmae", as it's a common typo.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a = a.chars().collect::<Vec<_>>();
+    let b = b.chars().collect::<Vec<_>>();
+
+    let mut prev2 = vec![];
+    let mut prev = (0..=b.len()).collect::<Vec<_>>();
+    for (i, ca) in a.iter().enumerate() {
+        let mut row = vec![i + 1];
+
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = prev[j] + usize::from(ca != cb);
+            let mut d = substitution.min(prev[j + 1] + 1).min(row[j] + 1);
+            if i > 0 && j > 0 && *ca == b[j - 1] && a[i - 1] == *cb {
+                d = d.min(prev2[j - 1] + 1);
+            }
+            row.push(d);
+        }
+
+        prev2 = std::mem::replace(&mut prev, row);
+    }
+
+    prev[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{closest, common_prefix, complete, edit_distance};
+    use crate::json::KeyOrder;
+    use serde_json::json;
+
+    #[test]
+    fn test_complete() {
+        let doc = json!({
+            "name": "jv",
+            "names": ["a", "b"],
+            "paths": {"/users": {}},
+            "id": 1
+        });
+
+        let segments = |path| {
+            complete(&doc, path, KeyOrder::Document)
+                .into_iter()
+                .map(|c| (c.segment, c.kind, c.has_children))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            segments("/na"),
+            vec![
+                ("name".to_string(), "string", false),
+                ("names".to_string(), "array", true)
+            ]
+        );
+        assert_eq!(segments("/").len(), 4);
+        assert_eq!(
+            segments("/names/"),
+            vec![
+                ("0".to_string(), "string", false),
+                ("1".to_string(), "string", false)
+            ]
+        );
+        assert_eq!(
+            segments("/paths/~1u"),
+            vec![("~1users".to_string(), "object", false)]
+        );
+        assert_eq!(
+            complete(&doc, "/", KeyOrder::Sorted)
+                .into_iter()
+                .map(|c| c.segment)
+                .collect::<Vec<_>>(),
+            vec!["id", "name", "names", "paths"]
+        );
+        assert_eq!(segments("/id/"), vec![]);
+        assert_eq!(segments("/missing/"), vec![]);
+    }
+
+    #[test]
+    fn test_common_prefix() {
+        let doc = json!({"status": 1, "statistics": 2, "stale": 3, "ñandú": 4, "ñu": 5});
+
+        assert_eq!(
+            common_prefix(&complete(&doc, "/stat", KeyOrder::Sorted)),
+            "stat"
+        );
+        assert_eq!(
+            common_prefix(&complete(&doc, "/s", KeyOrder::Sorted)),
+            "sta"
+        );
+        assert_eq!(common_prefix(&complete(&doc, "/ñ", KeyOrder::Sorted)), "ñ");
+        assert_eq!(
+            common_prefix(&complete(&doc, "/statu", KeyOrder::Sorted)),
+            "status"
+        );
+        assert_eq!(common_prefix(&complete(&doc, "/x", KeyOrder::Sorted)), "");
+    }
+
+    #[test]
+    fn test_closest() {
+        let doc = json!({"items": [{"name": "a", "status": "ok"}]});
+
+        assert_eq!(
+            closest(&doc, "/items/0/nmae"),
+            ("/items/0".to_string(), Some("name".to_string()))
+        );
+        assert_eq!(
+            closest(&doc, "/item/0"),
+            ("".to_string(), Some("items".to_string()))
+        );
+        assert_eq!(closest(&doc, "/items/3"), ("/items".to_string(), None));
+        assert_eq!(
+            closest(&doc, "/items/0/zzzzzz"),
+            ("/items/0".to_string(), None)
+        );
+        assert_eq!(closest(&doc, "/x"), ("".to_string(), None));
+        assert_eq!(
+            closest(&json!({"a": 1, "id": 2}), "/b"),
+            ("".to_string(), None)
+        );
+        assert_eq!(
+            closest(&doc, "/items/0/stats"),
+            ("/items/0".to_string(), Some("status".to_string()))
+        );
+        assert_eq!(
+            closest(&doc, "/items/0/name/x"),
+            ("/items/0/name".to_string(), None)
+        );
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("", ""), 0);
+        assert_eq!(edit_distance("name", "name"), 0);
+        assert_eq!(edit_distance("nmae", "name"), 1);
+        assert_eq!(edit_distance("ab", "ba"), 1);
+        assert_eq!(edit_distance("ca", "abc"), 3);
+        assert_eq!(edit_distance("stat", "status"), 2);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+    }
+}
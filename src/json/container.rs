@@ -0,0 +1,238 @@
+use crate::json::{JsonLine, JsonTokenTag};
+use crate::widgets::view::Line;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContainerKind {
+    Object,
+    Array,
+}
+
+/// An object or an array in the json lines along with the positions of its
+/// delimiters.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Container {
+    pub kind: ContainerKind,
+
+    /// (row, col) of the opening bracket.
+    pub start: (usize, usize),
+
+    /// (row, col) of the closing bracket.
+    pub end: (usize, usize),
+
+    /// How many containers enclose this one, the root container has depth 0.
+    pub depth: usize,
+
+    /// Number of keys for objects or items for arrays.
+    pub len: usize,
+}
+
+impl Container {
+    /// Whether the container spans more than one line and therefore can be
+    /// folded.
+    pub fn is_multiline(&self) -> bool {
+        self.start.0 != self.end.0
+    }
+
+    /// Whether the given row is between the opening and closing brackets, both
+    /// included.
+    pub fn contains_row(&self, row: usize) -> bool {
+        self.start.0 <= row && row <= self.end.0
+    }
+
+    /// Text to show right after the opening bracket when the container is
+    /// folded.
+    pub fn summary(&self) -> String {
+        let (close, what) = match self.kind {
+            ContainerKind::Object => ('}', "key"),
+            ContainerKind::Array => (']', "item"),
+        };
+
+        format!(
+            "…{} {} {}{}",
+            close,
+            self.len,
+            what,
+            if self.len == 1 { "" } else { "s" }
+        )
+    }
+}
+
+/// Find all the containers in the given json lines sorted by their starting
+/// position.
+pub fn containers(lines: &[JsonLine]) -> Vec<Container> {
+    let mut containers = vec![];
+
+    // indices into containers of the currently open ones
+    let mut stack: Vec<usize> = vec![];
+
+    for (r, line) in lines.iter().enumerate() {
+        let mut c = 0;
+
+        for tok in &line.tokens {
+            match tok.tag {
+                JsonTokenTag::ObjectStart | JsonTokenTag::ArrayStart => {
+                    count_array_item(&mut containers, &stack);
+
+                    let kind = if tok.tag == JsonTokenTag::ObjectStart {
+                        ContainerKind::Object
+                    } else {
+                        ContainerKind::Array
+                    };
+
+                    stack.push(containers.len());
+                    containers.push(Container {
+                        kind,
+                        start: (r, c),
+                        end: (r, c),
+                        depth: stack.len() - 1,
+                        len: 0,
+                    });
+                }
+                JsonTokenTag::ObjectEnd | JsonTokenTag::ArrayEnd => {
+                    if let Some(ix) = stack.pop() {
+                        containers[ix].end = (r, c);
+                    }
+                }
+                JsonTokenTag::ObjectKey => {
+                    if let Some(ix) = stack.last() {
+                        containers[*ix].len += 1;
+                    }
+                }
+                JsonTokenTag::Null
+                | JsonTokenTag::Number
+                | JsonTokenTag::Bool
+                | JsonTokenTag::String
+                | JsonTokenTag::DateTime
+                | JsonTokenTag::Binary
+                | JsonTokenTag::Ref => count_array_item(&mut containers, &stack),
+                _ => {}
+            }
+
+            c += tok.chars_count();
+        }
+    }
+
+    containers
+}
+
+/// Find the bracket matching the one at the given position or, if there's no
+/// bracket there, the one matching the first bracket after it on the same row.
+pub fn matching_bracket(containers: &[Container], pos: (usize, usize)) -> Option<(usize, usize)> {
+    let brackets = containers
+        .iter()
+        .flat_map(|c| [(c.start, c.end), (c.end, c.start)]);
+
+    brackets
+        .filter(|(b, _)| b.0 == pos.0 && b.1 >= pos.1)
+        .min()
+        .map(|(_, matching)| matching)
+}
+
+fn count_array_item(containers: &mut [Container], stack: &[usize]) {
+    if let Some(ix) = stack.last() {
+        let container = &mut containers[*ix];
+
+        if container.kind == ContainerKind::Array {
+            container.len += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{containers, matching_bracket, Container, ContainerKind};
+    use crate::json::{parse_json, KeyOrder};
+
+    #[test]
+    fn test_containers() {
+        let value = serde_json::from_str(r#"{"a": [1, {}, [true]], "b": {"c": null}}"#).unwrap();
+        let lines = parse_json(value, KeyOrder::Sorted);
+
+        // {
+        //     "a": [
+        //         1,
+        //         {},
+        //         [
+        //             true
+        //         ]
+        //     ],
+        //     "b": {
+        //         "c": null
+        //     }
+        // }
+        assert_eq!(
+            containers(&lines),
+            vec![
+                Container {
+                    kind: ContainerKind::Object,
+                    start: (0, 0),
+                    end: (11, 0),
+                    depth: 0,
+                    len: 2,
+                },
+                Container {
+                    kind: ContainerKind::Array,
+                    start: (1, 9),
+                    end: (7, 4),
+                    depth: 1,
+                    len: 3,
+                },
+                Container {
+                    kind: ContainerKind::Object,
+                    start: (3, 8),
+                    end: (3, 9),
+                    depth: 2,
+                    len: 0,
+                },
+                Container {
+                    kind: ContainerKind::Array,
+                    start: (4, 8),
+                    end: (6, 8),
+                    depth: 2,
+                    len: 1,
+                },
+                Container {
+                    kind: ContainerKind::Object,
+                    start: (8, 9),
+                    end: (10, 4),
+                    depth: 1,
+                    len: 1,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_matching_bracket() {
+        let value = serde_json::from_str(r#"{"a": [1, {}, [true]], "b": {"c": null}}"#).unwrap();
+        let containers = containers(&parse_json(value, KeyOrder::Sorted));
+
+        assert_eq!(matching_bracket(&containers, (0, 0)), Some((11, 0)));
+        assert_eq!(matching_bracket(&containers, (11, 0)), Some((0, 0)));
+        assert_eq!(matching_bracket(&containers, (1, 0)), Some((7, 4)));
+        assert_eq!(matching_bracket(&containers, (3, 8)), Some((3, 9)));
+        assert_eq!(matching_bracket(&containers, (3, 9)), Some((3, 8)));
+        assert_eq!(matching_bracket(&containers, (2, 0)), None);
+        assert_eq!(matching_bracket(&containers, (7, 5)), None);
+    }
+
+    #[test]
+    fn test_summary() {
+        let mut container = Container {
+            kind: ContainerKind::Object,
+            start: (0, 0),
+            end: (10, 0),
+            depth: 0,
+            len: 42,
+        };
+
+        assert_eq!(container.summary(), "…} 42 keys");
+
+        container.len = 1;
+        assert_eq!(container.summary(), "…} 1 key");
+
+        container.kind = ContainerKind::Array;
+        container.len = 1200;
+        assert_eq!(container.summary(), "…] 1200 items");
+    }
+}
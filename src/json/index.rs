@@ -1,31 +1,174 @@
 use std::collections::HashMap;
 
-use crate::json::{JsonLine, JsonTokenTag};
+use crate::json::{pointer, unquote, JsonLine, JsonTokenTag};
 use crate::widgets::view::Line;
 
 /// Index type from reference to (row, col)
 pub type Index = HashMap<String, (usize, usize)>;
 
-/// Create an index over the given json lines.
-pub fn index(lines: &[JsonLine]) -> Index {
+/// Reverse index from a (row, col) position to the reference of the innermost
+/// value at that position.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReverseIndex {
+    // sorted by start position
+    spans: Vec<Span>,
+}
+
+/// The positions a value spans. For object values the span starts at the key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Span {
+    start: (usize, usize),
+    end: (usize, usize),
+    parent: Option<usize>,
+    path: String,
+}
+
+impl ReverseIndex {
+    /// Get the reference of the innermost value that spans the given position.
+    pub fn get(&self, row: usize, col: usize) -> Option<&str> {
+        let i = self.innermost((row, col))?;
+        Some(&self.spans[i].path)
+    }
+
+    /// Get the start of the container around the innermost value at the given
+    /// position. As for all the positions returned by the structural motions,
+    /// the start is the key for object values.
+    pub fn parent(&self, row: usize, col: usize) -> Option<(usize, usize)> {
+        let i = self.innermost((row, col))?;
+        let parent = self.spans[i].parent?;
+
+        Some(self.spans[parent].start)
+    }
+
+    /// Get the start of the value after the innermost one at the given position
+    /// in the same container. Top level values, like the records of NDJSON,
+    /// are siblings too.
+    pub fn next_sibling(&self, row: usize, col: usize) -> Option<(usize, usize)> {
+        let i = self.innermost((row, col))?;
+        let parent = self.spans[i].parent;
+
+        self.spans[i + 1..]
+            .iter()
+            .take_while(|s| parent.is_none_or(|p| s.start <= self.spans[p].end))
+            .find(|s| s.parent == parent)
+            .map(|s| s.start)
+    }
+
+    /// Get the start of the value before the innermost one at the given
+    /// position in the same container.
+    pub fn prev_sibling(&self, row: usize, col: usize) -> Option<(usize, usize)> {
+        let i = self.innermost((row, col))?;
+        let parent = self.spans[i].parent;
+
+        self.spans[..i]
+            .iter()
+            .rev()
+            .take_while(|s| parent.is_none_or(|p| s.start >= self.spans[p].start))
+            .find(|s| s.parent == parent)
+            .map(|s| s.start)
+    }
+
+    /// Get the start of the first value inside the innermost container at the
+    /// given position.
+    pub fn first_child(&self, row: usize, col: usize) -> Option<(usize, usize)> {
+        let i = self.innermost((row, col))?;
+
+        // the children directly follow their container
+        self.spans
+            .get(i + 1)
+            .filter(|s| s.parent == Some(i))
+            .map(|s| s.start)
+    }
+
+    /// Get the start of the last value inside the innermost container at the
+    /// given position.
+    pub fn last_child(&self, row: usize, col: usize) -> Option<(usize, usize)> {
+        let i = self.innermost((row, col))?;
+        let end = self.spans[i].end;
+
+        self.spans[i + 1..]
+            .iter()
+            .take_while(|s| s.start <= end)
+            .filter(|s| s.parent == Some(i))
+            .last()
+            .map(|s| s.start)
+    }
+
+    /// Index into spans of the innermost value at the given position.
+    fn innermost(&self, p: (usize, usize)) -> Option<usize> {
+        let mut ix = self.spans.partition_point(|s| s.start <= p).checked_sub(1);
+
+        while let Some(i) = ix {
+            let span = &self.spans[i];
+            if span.end >= p {
+                return Some(i);
+            }
+
+            ix = span.parent;
+        }
+
+        None
+    }
+}
+
+/// Create an index over the given json lines along with the reverse index.
+pub fn index(lines: &[JsonLine]) -> (Index, ReverseIndex) {
+    index_values(lines, false)
+}
+
+/// Create an index over json lines made of many top level values, like the
+/// records of a NDJSON file, where each of them is referenced as if it was an
+/// item of an array, i.e. `#/<record>`.
+pub fn index_records(lines: &[JsonLine]) -> (Index, ReverseIndex) {
+    index_values(lines, true)
+}
+
+fn index_values(lines: &[JsonLine], records: bool) -> (Index, ReverseIndex) {
     let mut refs = HashMap::new();
+    let mut spans = vec![];
     let mut path = vec!["#".to_string()];
 
     // stack of the current array index if inside an array and whether the
     // current collection has at least one entry.
     let mut stack: Vec<(Option<usize>, bool)> = vec![];
 
+    // the records are the items of an implicit array around them
+    if records {
+        refs.insert("#".to_string(), (0, 0));
+        path.push("/".to_string());
+        stack.push((Some(0), false));
+    }
+    let top_level = stack.len();
+
+    // stack of the spans of the collections being visited and position of the
+    // last object key.
+    let mut spans_stack: Vec<usize> = vec![];
+    let mut key_pos = None;
+
     for (r, line) in lines.iter().enumerate() {
         let mut c = 0;
 
         for tok in &line.tokens {
             match tok.tag {
                 JsonTokenTag::ObjectStart | JsonTokenTag::ArrayStart => {
-                    if let Some((Some(ix), _)) = stack.last() {
-                        path.push(ix.to_string());
+                    if let Some((array_ix, has_entry)) = stack.last_mut() {
+                        *has_entry = true;
+
+                        if let Some(ix) = array_ix {
+                            path.push(ix.to_string());
+                        }
                     }
 
                     refs.insert(path.join(""), (r, c));
+
+                    spans_stack.push(spans.len());
+                    spans.push(Span {
+                        start: key_pos.take().unwrap_or((r, c)),
+                        end: (r, c),
+                        parent: spans_stack.iter().rev().nth(1).cloned(),
+                        path: path.join(""),
+                    });
+
                     path.push("/".to_string());
 
                     if tok.tag == JsonTokenTag::ArrayStart {
@@ -40,30 +183,28 @@ pub fn index(lines: &[JsonLine]) -> Index {
                         path.pop();
                     }
                     path.pop();
-                }
-                JsonTokenTag::Comma => {
-                    let (array_ix, has_entry) = stack.last_mut().unwrap();
-                    *has_entry = true;
 
-                    if let Some(array_ix) = array_ix {
-                        *array_ix += 1;
+                    if let Some(ix) = spans_stack.pop() {
+                        spans[ix].end = (r, c);
                     }
 
-                    path.pop();
+                    if records && stack.len() == top_level {
+                        next_entry(&mut stack, &mut path);
+                    }
                 }
+                JsonTokenTag::Comma => next_entry(&mut stack, &mut path),
                 JsonTokenTag::ObjectKey => {
-                    let mut k = tok.text.line().to_string();
-
-                    // remove ""
-                    k.remove(0);
-                    k.pop();
+                    let k = unquote(tok.text.line()).unwrap_or_default();
 
-                    path.push(k);
+                    path.push(pointer::escape(&k));
+                    key_pos = Some((r, c));
                 }
                 JsonTokenTag::Null
                 | JsonTokenTag::Number
                 | JsonTokenTag::Bool
                 | JsonTokenTag::String
+                | JsonTokenTag::DateTime
+                | JsonTokenTag::Binary
                 | JsonTokenTag::Ref => {
                     let (array_ix, has_entry) = stack.last_mut().unwrap();
                     *has_entry = true;
@@ -73,6 +214,27 @@ pub fn index(lines: &[JsonLine]) -> Index {
                     }
 
                     refs.insert(path.join(""), (r, c));
+
+                    spans.push(Span {
+                        start: key_pos.take().unwrap_or((r, c)),
+                        end: (r, c + tok.chars_count().saturating_sub(1)),
+                        parent: spans_stack.last().cloned(),
+                        path: path.join(""),
+                    });
+
+                    if records && stack.len() == top_level {
+                        next_entry(&mut stack, &mut path);
+                    }
+                }
+                JsonTokenTag::Invalid if records && stack.len() == top_level => {
+                    // the record can still be jumped to even though it has no
+                    // value
+                    if let Some((Some(ix), _)) = stack.last() {
+                        path.push(ix.to_string());
+                    }
+
+                    refs.insert(path.join(""), (r, c));
+                    next_entry(&mut stack, &mut path);
                 }
                 _ => {}
             }
@@ -81,5 +243,188 @@ pub fn index(lines: &[JsonLine]) -> Index {
         }
     }
 
-    refs
+    (refs, ReverseIndex { spans })
+}
+
+/// Find the (row, col) positions of all the object keys in the given lines.
+pub fn keys(lines: &[JsonLine]) -> Vec<(usize, usize)> {
+    let mut keys = vec![];
+
+    for (r, line) in lines.iter().enumerate() {
+        let mut c = 0;
+
+        for tok in &line.tokens {
+            if tok.tag == JsonTokenTag::ObjectKey {
+                keys.push((r, c));
+            }
+
+            c += tok.chars_count();
+        }
+    }
+
+    keys
+}
+
+/// Move past the current entry of the innermost collection.
+fn next_entry(stack: &mut [(Option<usize>, bool)], path: &mut Vec<String>) {
+    let (array_ix, has_entry) = stack.last_mut().unwrap();
+    *has_entry = true;
+
+    if let Some(array_ix) = array_ix {
+        *array_ix += 1;
+    }
+
+    path.pop();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{index, index_records, keys};
+    use crate::json::{parse_json, parse_ndjson, KeyOrder};
+
+    #[test]
+    fn test_reverse_index() {
+        let value = serde_json::from_str(r#"{"a": [1, {"b": null}], "c": "d"}"#).unwrap();
+        let lines = parse_json(value, KeyOrder::Sorted);
+
+        // {
+        //     "a": [
+        //         1,
+        //         {
+        //             "b": null
+        //         }
+        //     ],
+        //     "c": "d"
+        // }
+        let (index, reverse) = index(&lines);
+
+        assert_eq!(index.get("#"), Some(&(0, 0)));
+        assert_eq!(index.get("#/a"), Some(&(1, 9)));
+        assert_eq!(index.get("#/a/1/b"), Some(&(4, 17)));
+        assert_eq!(index.get("#/c"), Some(&(7, 9)));
+
+        assert_eq!(reverse.get(0, 0), Some("#"));
+        assert_eq!(reverse.get(1, 0), Some("#"));
+        assert_eq!(reverse.get(1, 4), Some("#/a"));
+        assert_eq!(reverse.get(1, 9), Some("#/a"));
+        assert_eq!(reverse.get(2, 8), Some("#/a/0"));
+        assert_eq!(reverse.get(2, 9), Some("#/a"));
+        assert_eq!(reverse.get(3, 8), Some("#/a/1"));
+        assert_eq!(reverse.get(4, 12), Some("#/a/1/b"));
+        assert_eq!(reverse.get(4, 20), Some("#/a/1/b"));
+        assert_eq!(reverse.get(4, 21), Some("#/a/1"));
+        assert_eq!(reverse.get(5, 8), Some("#/a/1"));
+        assert_eq!(reverse.get(6, 4), Some("#/a"));
+        assert_eq!(reverse.get(6, 5), Some("#"));
+        assert_eq!(reverse.get(7, 6), Some("#/c"));
+        assert_eq!(reverse.get(8, 0), Some("#"));
+        assert_eq!(reverse.get(9, 0), None);
+    }
+
+    #[test]
+    fn test_structural_motions() {
+        let value = serde_json::from_str(r#"{"a": [1, {"b": null}], "c": "d"}"#).unwrap();
+        let lines = parse_json(value, KeyOrder::Sorted);
+
+        // {
+        //     "a": [
+        //         1,
+        //         {
+        //             "b": null
+        //         }
+        //     ],
+        //     "c": "d"
+        // }
+        let (_, reverse) = index(&lines);
+
+        assert_eq!(reverse.parent(0, 0), None);
+        assert_eq!(reverse.parent(1, 4), Some((0, 0)));
+        assert_eq!(reverse.parent(4, 17), Some((3, 8)));
+        assert_eq!(reverse.parent(3, 8), Some((1, 4)));
+
+        assert_eq!(reverse.next_sibling(1, 4), Some((7, 4)));
+        assert_eq!(reverse.next_sibling(6, 4), Some((7, 4)));
+        assert_eq!(reverse.next_sibling(2, 8), Some((3, 8)));
+        assert_eq!(reverse.next_sibling(3, 8), None);
+        assert_eq!(reverse.next_sibling(7, 4), None);
+
+        assert_eq!(reverse.prev_sibling(7, 4), Some((1, 4)));
+        assert_eq!(reverse.prev_sibling(5, 8), Some((2, 8)));
+        assert_eq!(reverse.prev_sibling(2, 8), None);
+
+        assert_eq!(reverse.first_child(0, 0), Some((1, 4)));
+        assert_eq!(reverse.first_child(1, 9), Some((2, 8)));
+        assert_eq!(reverse.first_child(2, 8), None);
+        assert_eq!(reverse.last_child(0, 0), Some((7, 4)));
+        assert_eq!(reverse.last_child(1, 4), Some((3, 8)));
+        assert_eq!(reverse.last_child(7, 9), None);
+    }
+
+    #[test]
+    fn test_keys() {
+        let value = serde_json::from_str(r#"{"a": [1, {"b": null}], "c": "d"}"#).unwrap();
+        let lines = parse_json(value, KeyOrder::Sorted);
+
+        assert_eq!(keys(&lines), vec![(1, 4), (4, 12), (7, 4)]);
+    }
+
+    #[test]
+    fn test_index_after_nested_containers() {
+        let value = serde_json::from_str(r#"{"a": {"b": [1]}, "c": [[2]], "d": 3}"#).unwrap();
+        let lines = parse_json(value, KeyOrder::Sorted);
+
+        let (index, _) = index(&lines);
+
+        assert_eq!(index.get("#/a/b/0"), Some(&(3, 12)));
+        assert_eq!(index.get("#/c/0/0"), Some(&(8, 12)));
+        assert_eq!(index.get("#/d"), Some(&(11, 9)));
+    }
+
+    #[test]
+    fn test_index_escaped_keys() {
+        let value = serde_json::from_str(
+            r#"{"paths": {"/users/{id}": 1, "a~b": 2, "say \"hi\"": 3, "": 4}}"#,
+        )
+        .unwrap();
+        let lines = parse_json(value, KeyOrder::Document);
+
+        let (index, reverse) = index(&lines);
+
+        assert_eq!(index.get("#/paths/~1users~1{id}"), Some(&(2, 23)));
+        assert_eq!(index.get("#/paths/a~0b"), Some(&(3, 15)));
+        assert_eq!(index.get("#/paths/say \"hi\""), Some(&(4, 22)));
+        assert_eq!(index.get("#/paths/"), Some(&(5, 12)));
+
+        assert_eq!(reverse.get(2, 8), Some("#/paths/~1users~1{id}"));
+    }
+
+    #[test]
+    fn test_index_records() {
+        let (lines, _) = parse_ndjson("{\"a\": [true]}\n{\n42\n", KeyOrder::Sorted);
+
+        // {
+        //     "a": [
+        //         true
+        //     ]
+        // }
+        // {  ✗ EOF while parsing an object at line 1 column 1
+        // 42
+        let (index, reverse) = index_records(&lines);
+
+        assert_eq!(index.get("#"), Some(&(0, 0)));
+        assert_eq!(index.get("#/0"), Some(&(0, 0)));
+        assert_eq!(index.get("#/0/a/0"), Some(&(2, 8)));
+        assert_eq!(index.get("#/1"), Some(&(5, 0)));
+        assert_eq!(index.get("#/2"), Some(&(6, 0)));
+        assert_eq!(index.get("#/3"), None);
+
+        assert_eq!(reverse.get(0, 0), Some("#/0"));
+        assert_eq!(reverse.get(2, 8), Some("#/0/a/0"));
+        assert_eq!(reverse.get(4, 0), Some("#/0"));
+        assert_eq!(reverse.get(5, 0), None);
+        assert_eq!(reverse.get(6, 1), Some("#/2"));
+
+        assert_eq!(reverse.next_sibling(0, 0), Some((6, 0)));
+        assert_eq!(reverse.prev_sibling(6, 0), Some((0, 0)));
+    }
 }
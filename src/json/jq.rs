@@ -0,0 +1,941 @@
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::fmt;
+use std::iter::Peekable;
+use std::str::{Chars, FromStr};
+
+use serde_json::{Number, Value};
+
+use crate::json::pointer;
+
+/// A query written in a small subset of the jq language: paths like
+/// `.items[0].name`, `.[]`, `..`, pipes, commas, comparisons, `and`, `or`,
+/// `not`, `select`, `map`, `keys`, `length` and `empty`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Query {
+    expr: Expr,
+}
+
+/// A value produced by a query along with where it comes from in the queried
+/// document, if it does.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Output<'a> {
+    pub value: Cow<'a, Value>,
+    path: Option<Vec<PathSegment>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum PathSegment {
+    Key(String),
+    Index(usize),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    Identity,
+    Recurse,
+    Field(Box<Expr>, String),
+    Index(Box<Expr>, Box<Expr>),
+    Iterate(Box<Expr>),
+    Optional(Box<Expr>),
+
+    // segment of a "/" separated path, it's a key for objects and an index for
+    // arrays
+    Segment(Box<Expr>, String),
+
+    Pipe(Box<Expr>, Box<Expr>),
+    Comma(Box<Expr>, Box<Expr>),
+    Literal(Value),
+    Array(Option<Box<Expr>>),
+    Compare(Box<Expr>, CmpOp, Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not,
+    Select(Box<Expr>),
+    Map(Box<Expr>),
+    Keys,
+    Length,
+    Empty,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CmpOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Dot,
+    Recurse,
+    Field(String),
+    Ident(String),
+    Str(String),
+    Num(f64),
+    LBracket,
+    RBracket,
+    LParen,
+    RParen,
+    Pipe,
+    Comma,
+    Question,
+    Cmp(CmpOp),
+}
+
+impl Query {
+    /// Create a query from a "/" separated path where "*" matches all the
+    /// items of an array or all the values of an object, e.g. `/items/*/name`.
+    /// The keys are escaped like in JSON Pointers, so "~1" stands for "/".
+    pub fn from_path(path: &str) -> Self {
+        let expr = path
+            .split('/')
+            .filter(|s| !s.is_empty())
+            .fold(Expr::Identity, |e, s| match s {
+                "*" => Expr::Iterate(Box::new(e)),
+                s => Expr::Segment(Box::new(e), pointer::unescape(s)),
+            });
+
+        Query { expr }
+    }
+
+    /// Run the query against the given value.
+    pub fn run<'a>(&self, value: &'a Value) -> Result<Vec<Output<'a>>, String> {
+        eval(&self.expr, Output::root(value))
+    }
+}
+
+impl FromStr for Query {
+    type Err = String;
+
+    fn from_str(q: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser {
+            tokens: tokenize(q)?,
+            pos: 0,
+        };
+
+        let expr = parser.parse_pipe()?;
+        match parser.tokens.get(parser.pos) {
+            None => Ok(Query { expr }),
+            Some(t) => Err(format!("unexpected {}", t)),
+        }
+    }
+}
+
+impl<'a> Output<'a> {
+    /// The reference of the value in the queried document, e.g. `#/items/0`,
+    /// if it comes from there.
+    pub fn reference(&self) -> Option<String> {
+        let path = self.path.as_ref()?;
+
+        let mut reference = "#".to_string();
+        for s in path {
+            reference.push('/');
+            match s {
+                PathSegment::Key(k) => reference.push_str(&pointer::escape(k)),
+                PathSegment::Index(i) => reference.push_str(&i.to_string()),
+            }
+        }
+
+        Some(reference)
+    }
+
+    /// The whole document being queried.
+    pub(crate) fn root(value: &'a Value) -> Self {
+        Output {
+            value: Cow::Borrowed(value),
+            path: Some(vec![]),
+        }
+    }
+
+    fn new(value: Value) -> Self {
+        Output {
+            value: Cow::Owned(value),
+            path: None,
+        }
+    }
+
+    /// Get the child at the given segment, `null` if it doesn't exist.
+    pub(crate) fn child(&self, segment: PathSegment) -> Output<'a> {
+        let value = match &self.value {
+            Cow::Borrowed(v) => Cow::Borrowed(lookup(v, &segment).unwrap_or(&Value::Null)),
+            Cow::Owned(v) => Cow::Owned(lookup(v, &segment).cloned().unwrap_or(Value::Null)),
+        };
+
+        let path = self.path.as_ref().map(|p| {
+            let mut p = p.clone();
+            p.push(segment);
+            p
+        });
+
+        Output { value, path }
+    }
+
+    pub(crate) fn children(&self) -> Result<Vec<Output<'a>>, String> {
+        match self.value.as_ref() {
+            Value::Array(arr) => Ok((0..arr.len())
+                .map(|i| self.child(PathSegment::Index(i)))
+                .collect()),
+            Value::Object(obj) => Ok(obj
+                .keys()
+                .map(|k| self.child(PathSegment::Key(k.clone())))
+                .collect()),
+            v => Err(format!("cannot iterate over {}", type_name(v))),
+        }
+    }
+}
+
+fn lookup<'v>(value: &'v Value, segment: &PathSegment) -> Option<&'v Value> {
+    match segment {
+        PathSegment::Key(k) => value.get(k),
+        PathSegment::Index(i) => value.get(i),
+    }
+}
+
+fn eval<'a>(expr: &Expr, input: Output<'a>) -> Result<Vec<Output<'a>>, String> {
+    let outputs = match expr {
+        Expr::Identity => vec![input],
+        Expr::Recurse => {
+            let mut outputs = vec![];
+            recurse(input, &mut outputs);
+            outputs
+        }
+        Expr::Field(e, name) => {
+            let mut outputs = vec![];
+
+            for o in eval(e, input)? {
+                match o.value.as_ref() {
+                    Value::Object(_) | Value::Null => {
+                        outputs.push(o.child(PathSegment::Key(name.clone())))
+                    }
+                    v => return Err(format!("cannot index {} with \"{}\"", type_name(v), name)),
+                }
+            }
+
+            outputs
+        }
+        Expr::Segment(e, segment) => {
+            let mut outputs = vec![];
+
+            for o in eval(e, input)? {
+                let segment = match o.value.as_ref() {
+                    Value::Object(obj) if obj.contains_key(segment) => {
+                        PathSegment::Key(segment.clone())
+                    }
+                    Value::Array(arr) => match segment.parse() {
+                        Ok(i) if i < arr.len() => PathSegment::Index(i),
+                        _ => continue,
+                    },
+                    _ => continue,
+                };
+
+                outputs.push(o.child(segment));
+            }
+
+            outputs
+        }
+        Expr::Index(e, ix) => {
+            let mut outputs = vec![];
+
+            for o in eval(e, input.clone())? {
+                for i in eval(ix, input.clone())? {
+                    outputs.push(index(&o, &i.value)?);
+                }
+            }
+
+            outputs
+        }
+        Expr::Iterate(e) => {
+            let mut outputs = vec![];
+            for o in eval(e, input)? {
+                outputs.extend(o.children()?);
+            }
+            outputs
+        }
+        Expr::Optional(e) => eval(e, input).unwrap_or_default(),
+        Expr::Pipe(lhs, rhs) => {
+            let mut outputs = vec![];
+            for o in eval(lhs, input)? {
+                outputs.extend(eval(rhs, o)?);
+            }
+            outputs
+        }
+        Expr::Comma(lhs, rhs) => {
+            let mut outputs = eval(lhs, input.clone())?;
+            outputs.extend(eval(rhs, input)?);
+            outputs
+        }
+        Expr::Literal(v) => vec![Output::new(v.clone())],
+        Expr::Array(None) => vec![Output::new(Value::Array(vec![]))],
+        Expr::Array(Some(e)) => {
+            let items = eval(e, input)?;
+            vec![Output::new(Value::Array(
+                items.into_iter().map(|o| o.value.into_owned()).collect(),
+            ))]
+        }
+        Expr::Compare(lhs, op, rhs) => {
+            let mut outputs = vec![];
+
+            for r in eval(rhs, input.clone())? {
+                for l in eval(lhs, input.clone())? {
+                    let ord = compare(&l.value, &r.value);
+                    let res = match op {
+                        CmpOp::Eq => ord == Ordering::Equal,
+                        CmpOp::Ne => ord != Ordering::Equal,
+                        CmpOp::Lt => ord == Ordering::Less,
+                        CmpOp::Le => ord != Ordering::Greater,
+                        CmpOp::Gt => ord == Ordering::Greater,
+                        CmpOp::Ge => ord != Ordering::Less,
+                    };
+
+                    outputs.push(Output::new(Value::Bool(res)));
+                }
+            }
+
+            outputs
+        }
+        Expr::And(lhs, rhs) | Expr::Or(lhs, rhs) => {
+            let is_and = matches!(expr, Expr::And(_, _));
+            let mut outputs = vec![];
+
+            for l in eval(lhs, input.clone())? {
+                // short circuit like jq does
+                if is_and != is_truthy(&l.value) {
+                    outputs.push(Output::new(Value::Bool(!is_and)));
+                    continue;
+                }
+
+                for r in eval(rhs, input.clone())? {
+                    outputs.push(Output::new(Value::Bool(is_truthy(&r.value))));
+                }
+            }
+
+            outputs
+        }
+        Expr::Not => vec![Output::new(Value::Bool(!is_truthy(&input.value)))],
+        Expr::Select(cond) => {
+            let mut outputs = vec![];
+
+            for c in eval(cond, input.clone())? {
+                if is_truthy(&c.value) {
+                    outputs.push(input.clone());
+                }
+            }
+
+            outputs
+        }
+        Expr::Map(f) => {
+            let mut items = vec![];
+            for child in input.children()? {
+                items.extend(eval(f, child)?.into_iter().map(|o| o.value.into_owned()));
+            }
+
+            vec![Output::new(Value::Array(items))]
+        }
+        Expr::Keys => {
+            let keys = match input.value.as_ref() {
+                Value::Object(obj) => {
+                    let mut keys = obj.keys().cloned().collect::<Vec<_>>();
+                    keys.sort();
+                    keys.into_iter().map(Value::String).collect()
+                }
+                Value::Array(arr) => (0..arr.len()).map(Value::from).collect(),
+                v => return Err(format!("{} has no keys", type_name(v))),
+            };
+
+            vec![Output::new(Value::Array(keys))]
+        }
+        Expr::Length => {
+            let len = match input.value.as_ref() {
+                Value::Null => Value::from(0),
+                Value::Number(n) => number(n.as_f64().unwrap_or(0.0).abs()),
+                Value::String(s) => Value::from(s.chars().count()),
+                Value::Array(arr) => Value::from(arr.len()),
+                Value::Object(obj) => Value::from(obj.len()),
+                v => return Err(format!("{} has no length", type_name(v))),
+            };
+
+            vec![Output::new(len)]
+        }
+        Expr::Empty => vec![],
+    };
+
+    Ok(outputs)
+}
+
+/// Collect the given value and all its descendants, parents first.
+fn recurse<'a>(o: Output<'a>, outputs: &mut Vec<Output<'a>>) {
+    let children = o.children().unwrap_or_default();
+
+    outputs.push(o);
+    for c in children {
+        recurse(c, outputs);
+    }
+}
+
+fn index<'a>(o: &Output<'a>, ix: &Value) -> Result<Output<'a>, String> {
+    match (o.value.as_ref(), ix) {
+        (Value::Array(arr), Value::Number(n)) => {
+            let n = n.as_f64().unwrap_or(0.0);
+
+            // negative indices count from the end
+            let i = if n < 0.0 { arr.len() as f64 + n } else { n };
+
+            if i >= 0.0 && i.fract() == 0.0 && (i as usize) < arr.len() {
+                Ok(o.child(PathSegment::Index(i as usize)))
+            } else {
+                Ok(Output::new(Value::Null))
+            }
+        }
+        (Value::Object(_), Value::String(k)) | (Value::Null, Value::String(k)) => {
+            Ok(o.child(PathSegment::Key(k.clone())))
+        }
+        (Value::Null, Value::Number(_)) => Ok(Output::new(Value::Null)),
+        (v, ix) => Err(format!(
+            "cannot index {} with {}",
+            type_name(v),
+            type_name(ix)
+        )),
+    }
+}
+
+fn is_truthy(v: &Value) -> bool {
+    !matches!(v, Value::Null | Value::Bool(false))
+}
+
+pub(crate) fn type_name(v: &Value) -> &'static str {
+    match v {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+/// Compare two values using the same order as jq: null, false, true, numbers,
+/// strings, arrays and objects.
+fn compare(a: &Value, b: &Value) -> Ordering {
+    fn rank(v: &Value) -> u8 {
+        match v {
+            Value::Null => 0,
+            Value::Bool(false) => 1,
+            Value::Bool(true) => 2,
+            Value::Number(_) => 3,
+            Value::String(_) => 4,
+            Value::Array(_) => 5,
+            Value::Object(_) => 6,
+        }
+    }
+
+    match (a, b) {
+        (Value::Number(x), Value::Number(y)) => x
+            .as_f64()
+            .partial_cmp(&y.as_f64())
+            .unwrap_or(Ordering::Equal),
+        (Value::String(x), Value::String(y)) => x.cmp(y),
+        (Value::Array(x), Value::Array(y)) => x
+            .iter()
+            .zip(y)
+            .map(|(a, b)| compare(a, b))
+            .find(|o| *o != Ordering::Equal)
+            .unwrap_or_else(|| x.len().cmp(&y.len())),
+        (Value::Object(x), Value::Object(y)) => {
+            let mut xkeys = x.keys().collect::<Vec<_>>();
+            let mut ykeys = y.keys().collect::<Vec<_>>();
+            xkeys.sort();
+            ykeys.sort();
+
+            xkeys.cmp(&ykeys).then_with(|| {
+                xkeys
+                    .iter()
+                    .map(|k| compare(&x[k.as_str()], &y[k.as_str()]))
+                    .find(|o| *o != Ordering::Equal)
+                    .unwrap_or(Ordering::Equal)
+            })
+        }
+        _ => rank(a).cmp(&rank(b)),
+    }
+}
+
+/// Convert the given number to a JSON number preferring integers.
+fn number(n: f64) -> Value {
+    if n.fract() == 0.0 && n.abs() < i64::MAX as f64 {
+        Value::from(n as i64)
+    } else {
+        Number::from_f64(n).map_or(Value::Null, Value::Number)
+    }
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let t = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        t
+    }
+
+    fn eat(&mut self, t: &Token) -> bool {
+        if self.peek() == Some(t) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, t: &Token) -> Result<(), String> {
+        match self.next() {
+            Some(ref n) if n == t => Ok(()),
+            Some(n) => Err(format!("expected {} but found {}", t, n)),
+            None => Err(format!("expected {} but the query ended", t)),
+        }
+    }
+
+    fn parse_pipe(&mut self) -> Result<Expr, String> {
+        let lhs = self.parse_comma()?;
+
+        if self.eat(&Token::Pipe) {
+            Ok(Expr::Pipe(Box::new(lhs), Box::new(self.parse_pipe()?)))
+        } else {
+            Ok(lhs)
+        }
+    }
+
+    fn parse_comma(&mut self) -> Result<Expr, String> {
+        let mut e = self.parse_or()?;
+
+        while self.eat(&Token::Comma) {
+            e = Expr::Comma(Box::new(e), Box::new(self.parse_or()?));
+        }
+
+        Ok(e)
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, String> {
+        let mut e = self.parse_and()?;
+
+        while self.eat(&Token::Ident("or".to_string())) {
+            e = Expr::Or(Box::new(e), Box::new(self.parse_and()?));
+        }
+
+        Ok(e)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, String> {
+        let mut e = self.parse_compare()?;
+
+        while self.eat(&Token::Ident("and".to_string())) {
+            e = Expr::And(Box::new(e), Box::new(self.parse_compare()?));
+        }
+
+        Ok(e)
+    }
+
+    fn parse_compare(&mut self) -> Result<Expr, String> {
+        let lhs = self.parse_postfix()?;
+
+        match self.peek() {
+            Some(Token::Cmp(op)) => {
+                let op = *op;
+                self.pos += 1;
+
+                let rhs = self.parse_postfix()?;
+                Ok(Expr::Compare(Box::new(lhs), op, Box::new(rhs)))
+            }
+            _ => Ok(lhs),
+        }
+    }
+
+    fn parse_postfix(&mut self) -> Result<Expr, String> {
+        let mut e = self.parse_term()?;
+
+        loop {
+            e = match self.peek() {
+                Some(Token::Field(name)) => {
+                    let name = name.clone();
+                    self.pos += 1;
+                    Expr::Field(Box::new(e), name)
+                }
+                Some(Token::Dot) => match self.tokens.get(self.pos + 1) {
+                    Some(Token::Str(name)) => {
+                        let name = name.clone();
+                        self.pos += 2;
+                        Expr::Field(Box::new(e), name)
+                    }
+                    Some(Token::LBracket) => {
+                        self.pos += 1;
+                        continue;
+                    }
+                    _ => break,
+                },
+                Some(Token::LBracket) => {
+                    self.pos += 1;
+
+                    if self.eat(&Token::RBracket) {
+                        Expr::Iterate(Box::new(e))
+                    } else {
+                        let ix = self.parse_pipe()?;
+                        self.expect(&Token::RBracket)?;
+                        Expr::Index(Box::new(e), Box::new(ix))
+                    }
+                }
+                Some(Token::Question) => {
+                    self.pos += 1;
+                    Expr::Optional(Box::new(e))
+                }
+                _ => break,
+            };
+        }
+
+        Ok(e)
+    }
+
+    fn parse_term(&mut self) -> Result<Expr, String> {
+        match self.next() {
+            Some(Token::Dot) => match self.peek() {
+                Some(Token::Str(name)) => {
+                    let name = name.clone();
+                    self.pos += 1;
+                    Ok(Expr::Field(Box::new(Expr::Identity), name))
+                }
+                _ => Ok(Expr::Identity),
+            },
+            Some(Token::Field(name)) => Ok(Expr::Field(Box::new(Expr::Identity), name)),
+            Some(Token::Recurse) => Ok(Expr::Recurse),
+            Some(Token::Str(s)) => Ok(Expr::Literal(Value::String(s))),
+            Some(Token::Num(n)) => Ok(Expr::Literal(number(n))),
+            Some(Token::LParen) => {
+                let e = self.parse_pipe()?;
+                self.expect(&Token::RParen)?;
+                Ok(e)
+            }
+            Some(Token::LBracket) => {
+                if self.eat(&Token::RBracket) {
+                    return Ok(Expr::Array(None));
+                }
+
+                let e = self.parse_pipe()?;
+                self.expect(&Token::RBracket)?;
+                Ok(Expr::Array(Some(Box::new(e))))
+            }
+            Some(Token::Ident(name)) => match name.as_str() {
+                "true" => Ok(Expr::Literal(Value::Bool(true))),
+                "false" => Ok(Expr::Literal(Value::Bool(false))),
+                "null" => Ok(Expr::Literal(Value::Null)),
+                "not" => Ok(Expr::Not),
+                "keys" => Ok(Expr::Keys),
+                "length" => Ok(Expr::Length),
+                "empty" => Ok(Expr::Empty),
+                "select" | "map" => {
+                    self.expect(&Token::LParen)?;
+                    let arg = Box::new(self.parse_pipe()?);
+                    self.expect(&Token::RParen)?;
+
+                    if name == "select" {
+                        Ok(Expr::Select(arg))
+                    } else {
+                        Ok(Expr::Map(arg))
+                    }
+                }
+                _ => Err(format!("unknown function {}", name)),
+            },
+            Some(t) => Err(format!("unexpected {}", t)),
+            None => Err("unexpected end of the query".to_string()),
+        }
+    }
+}
+
+fn tokenize(q: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = vec![];
+    let mut chars = q.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        let token = match c {
+            c if c.is_whitespace() => continue,
+            '.' => match chars.peek() {
+                Some('.') => {
+                    chars.next();
+                    Token::Recurse
+                }
+                Some(c) if is_ident_start(*c) => Token::Field(ident(&mut chars)),
+                _ => Token::Dot,
+            },
+            '[' => Token::LBracket,
+            ']' => Token::RBracket,
+            '(' => Token::LParen,
+            ')' => Token::RParen,
+            '|' => Token::Pipe,
+            ',' => Token::Comma,
+            '?' => Token::Question,
+            '"' => Token::Str(string(&mut chars)?),
+            '=' | '!' | '<' | '>' => {
+                let eq = chars.peek() == Some(&'=');
+                if eq {
+                    chars.next();
+                }
+
+                Token::Cmp(match (c, eq) {
+                    ('=', true) => CmpOp::Eq,
+                    ('!', true) => CmpOp::Ne,
+                    ('<', false) => CmpOp::Lt,
+                    ('<', true) => CmpOp::Le,
+                    ('>', false) => CmpOp::Gt,
+                    ('>', true) => CmpOp::Ge,
+                    _ => return Err(format!("unexpected character '{}'", c)),
+                })
+            }
+            c if c.is_ascii_digit()
+                || (c == '-' && chars.peek().is_some_and(char::is_ascii_digit)) =>
+            {
+                let mut n = c.to_string();
+                while let Some(&c) = chars.peek() {
+                    let exponent_sign = (c == '-' || c == '+') && n.ends_with(['e', 'E']);
+                    if !c.is_ascii_digit() && !matches!(c, '.' | 'e' | 'E') && !exponent_sign {
+                        break;
+                    }
+
+                    n.push(c);
+                    chars.next();
+                }
+
+                Token::Num(n.parse().map_err(|_| format!("invalid number {}", n))?)
+            }
+            c if is_ident_start(c) => {
+                let mut name = c.to_string();
+                name.push_str(&ident(&mut chars));
+                Token::Ident(name)
+            }
+            c => return Err(format!("unexpected character '{}'", c)),
+        };
+
+        tokens.push(token);
+    }
+
+    Ok(tokens)
+}
+
+fn is_ident_start(c: char) -> bool {
+    c.is_alphabetic() || c == '_'
+}
+
+fn ident(chars: &mut Peekable<Chars>) -> String {
+    let mut name = String::new();
+
+    while let Some(&c) = chars.peek() {
+        if !c.is_alphanumeric() && c != '_' {
+            break;
+        }
+
+        name.push(c);
+        chars.next();
+    }
+
+    name
+}
+
+/// Parse a string literal whose opening quote has already been consumed.
+fn string(chars: &mut Peekable<Chars>) -> Result<String, String> {
+    let mut s = String::new();
+
+    loop {
+        match chars.next() {
+            None => return Err("unterminated string".to_string()),
+            Some('"') => return Ok(s),
+            Some('\\') => {
+                let c = match chars.next() {
+                    Some('n') => '\n',
+                    Some('t') => '\t',
+                    Some('r') => '\r',
+                    Some('b') => '\u{8}',
+                    Some('f') => '\u{c}',
+                    Some('u') => {
+                        let hex = chars.by_ref().take(4).collect::<String>();
+                        u32::from_str_radix(&hex, 16)
+                            .ok()
+                            .and_then(std::char::from_u32)
+                            .ok_or_else(|| format!("invalid unicode escape \\u{}", hex))?
+                    }
+                    Some(c @ '"') | Some(c @ '\\') | Some(c @ '/') => c,
+                    Some(c) => return Err(format!("invalid escape \\{}", c)),
+                    None => return Err("unterminated string".to_string()),
+                };
+
+                s.push(c);
+            }
+            Some(c) => s.push(c),
+        }
+    }
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Token::Dot => write!(f, "'.'"),
+            Token::Recurse => write!(f, "'..'"),
+            Token::Field(name) => write!(f, "'.{}'", name),
+            Token::Ident(name) => write!(f, "'{}'", name),
+            Token::Str(s) => write!(f, "\"{}\"", s),
+            Token::Num(n) => write!(f, "{}", n),
+            Token::LBracket => write!(f, "'['"),
+            Token::RBracket => write!(f, "']'"),
+            Token::LParen => write!(f, "'('"),
+            Token::RParen => write!(f, "')'"),
+            Token::Pipe => write!(f, "'|'"),
+            Token::Comma => write!(f, "','"),
+            Token::Question => write!(f, "'?'"),
+            Token::Cmp(op) => write!(
+                f,
+                "'{}'",
+                match op {
+                    CmpOp::Eq => "==",
+                    CmpOp::Ne => "!=",
+                    CmpOp::Lt => "<",
+                    CmpOp::Le => "<=",
+                    CmpOp::Gt => ">",
+                    CmpOp::Ge => ">=",
+                }
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Query;
+    use serde_json::{json, Value};
+
+    fn run(q: &str, value: &Value) -> Vec<(Value, Option<String>)> {
+        q.parse::<Query>()
+            .unwrap()
+            .run(value)
+            .unwrap()
+            .into_iter()
+            .map(|o| (o.value.clone().into_owned(), o.reference()))
+            .collect()
+    }
+
+    #[test]
+    fn test_paths() {
+        let doc = json!({"items": [{"name": "a"}, {"name": "b", "tags": ["x"]}], "n": 1});
+
+        assert_eq!(run(".", &doc), vec![(doc.clone(), Some("#".to_string()))]);
+        assert_eq!(
+            run(".items[1].name", &doc),
+            vec![(json!("b"), Some("#/items/1/name".to_string()))]
+        );
+        assert_eq!(
+            run(".items[-1] | .\"tags\"[0]", &doc),
+            vec![(json!("x"), Some("#/items/1/tags/0".to_string()))]
+        );
+        assert_eq!(
+            run(".items[].name", &doc),
+            vec![
+                (json!("a"), Some("#/items/0/name".to_string())),
+                (json!("b"), Some("#/items/1/name".to_string())),
+            ]
+        );
+        assert_eq!(
+            run(".n, .missing", &doc),
+            vec![
+                (json!(1), Some("#/n".to_string())),
+                (Value::Null, Some("#/missing".to_string())),
+            ]
+        );
+        assert_eq!(
+            run(".. | .name? | select(. != null)", &doc)
+                .into_iter()
+                .map(|(_, r)| r.unwrap())
+                .collect::<Vec<_>>(),
+            vec!["#/items/0/name", "#/items/1/name"]
+        );
+    }
+
+    #[test]
+    fn test_functions() {
+        let doc = json!([
+            {"id": 1, "status": "ok"},
+            {"id": 2, "status": "failed"},
+            {"id": 3, "status": "failed"}
+        ]);
+
+        assert_eq!(
+            run(".[] | select(.status == \"failed\") | .id", &doc),
+            vec![
+                (json!(2), Some("#/1/id".to_string())),
+                (json!(3), Some("#/2/id".to_string())),
+            ]
+        );
+        assert_eq!(
+            run(".[] | select(.id > 1 and .id <= 2)", &doc),
+            vec![(doc[1].clone(), Some("#/1".to_string()))]
+        );
+        assert_eq!(run("map(.id)", &doc), vec![(json!([1, 2, 3]), None)]);
+        assert_eq!(run("length", &doc), vec![(json!(3), None)]);
+        assert_eq!(
+            run(".[0] | keys", &doc),
+            vec![(json!(["id", "status"]), None)]
+        );
+        assert_eq!(
+            run("[.[] | .id == 1 | not]", &doc),
+            vec![(json!([false, true, true]), None)]
+        );
+    }
+
+    #[test]
+    fn test_from_path() {
+        let doc = json!({"items": [{"name": "a"}, {"id": 2}, {"name": "c"}]});
+
+        let results = Query::from_path("/items/*/name")
+            .run(&doc)
+            .unwrap()
+            .into_iter()
+            .map(|o| o.reference().unwrap())
+            .collect::<Vec<_>>();
+
+        assert_eq!(results, vec!["#/items/0/name", "#/items/2/name"]);
+
+        let doc = json!({"paths": {"/users/{id}": {"get": {}}, "a~b": 1}});
+
+        let results = Query::from_path("/paths/*")
+            .run(&doc)
+            .unwrap()
+            .into_iter()
+            .map(|o| o.reference().unwrap())
+            .collect::<Vec<_>>();
+
+        assert_eq!(results, vec!["#/paths/~1users~1{id}", "#/paths/a~0b"]);
+
+        let get = Query::from_path("/paths/~1users~1{id}/get").run(&doc);
+        assert_eq!(
+            get.unwrap()[0].reference().unwrap(),
+            "#/paths/~1users~1{id}/get"
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(".[".parse::<Query>().is_err());
+        assert!("select(.a".parse::<Query>().is_err());
+        assert!("frobnicate".parse::<Query>().is_err());
+        assert!(". ==".parse::<Query>().is_err());
+        assert!("\"unterminated".parse::<Query>().is_err());
+
+        let doc = json!({"a": 1});
+        assert!(".a.b".parse::<Query>().unwrap().run(&doc).is_err());
+        assert!(".a[]".parse::<Query>().unwrap().run(&doc).is_err());
+        assert_eq!(run(".a[]?", &doc), vec![]);
+    }
+}
@@ -0,0 +1,655 @@
+use std::str::FromStr;
+
+use serde_json::Value;
+
+use crate::json::jq::{Output, PathSegment};
+
+/// A JSONPath query like `$..name`, `$.items[?(@.age > 30)]` or
+/// `$.items[1:10:2]`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonPath {
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Child(Vec<Selector>),
+
+    // the selectors are applied to the node and to all its descendants
+    Descendant(Vec<Selector>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Selector {
+    Name(String),
+    Wildcard,
+    Index(i64),
+    Slice(Option<i64>, Option<i64>, Option<i64>),
+    Filter(Filter),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Filter {
+    Exists(RelativePath),
+    Compare(Operand, CmpOp, Operand),
+    And(Box<Filter>, Box<Filter>),
+    Or(Box<Filter>, Box<Filter>),
+    Not(Box<Filter>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Operand {
+    Path(RelativePath),
+    Literal(Value),
+}
+
+/// A path inside a filter that starts either from the current node `@` or
+/// from the root `$`.
+#[derive(Debug, Clone, PartialEq)]
+struct RelativePath {
+    absolute: bool,
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CmpOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl JsonPath {
+    /// Find all the nodes of the given value the path matches, in document
+    /// order.
+    pub fn run<'a>(&self, value: &'a Value) -> Vec<Output<'a>> {
+        let root = Output::root(value);
+
+        select(&self.segments, vec![root.clone()], &root)
+    }
+}
+
+impl FromStr for JsonPath {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser {
+            chars: s.chars().collect(),
+            pos: 0,
+        };
+
+        parser.skip_ws();
+        parser.expect('$')?;
+        let segments = parser.parse_segments()?;
+
+        parser.skip_ws();
+        match parser.peek() {
+            None => Ok(JsonPath { segments }),
+            Some(c) => Err(format!("unexpected character '{}'", c)),
+        }
+    }
+}
+
+fn select<'a>(
+    segments: &[Segment],
+    mut nodes: Vec<Output<'a>>,
+    root: &Output<'a>,
+) -> Vec<Output<'a>> {
+    for segment in segments {
+        nodes = nodes
+            .iter()
+            .flat_map(|n| match segment {
+                Segment::Child(selectors) => apply(selectors, n, root),
+                Segment::Descendant(selectors) => {
+                    let mut descendants = vec![];
+                    collect_descendants(n.clone(), &mut descendants);
+
+                    descendants
+                        .iter()
+                        .flat_map(|d| apply(selectors, d, root))
+                        .collect()
+                }
+            })
+            .collect();
+    }
+
+    nodes
+}
+
+fn collect_descendants<'a>(node: Output<'a>, descendants: &mut Vec<Output<'a>>) {
+    let children = node.children().unwrap_or_default();
+
+    descendants.push(node);
+    for c in children {
+        collect_descendants(c, descendants);
+    }
+}
+
+fn apply<'a>(selectors: &[Selector], node: &Output<'a>, root: &Output<'a>) -> Vec<Output<'a>> {
+    let mut selected = vec![];
+
+    for selector in selectors {
+        match (selector, node.value.as_ref()) {
+            (Selector::Name(k), Value::Object(obj)) if obj.contains_key(k) => {
+                selected.push(node.child(PathSegment::Key(k.clone())));
+            }
+            (Selector::Wildcard, _) => selected.extend(node.children().unwrap_or_default()),
+            (Selector::Index(i), Value::Array(arr)) => {
+                let i = if *i < 0 { arr.len() as i64 + i } else { *i };
+
+                if i >= 0 && (i as usize) < arr.len() {
+                    selected.push(node.child(PathSegment::Index(i as usize)));
+                }
+            }
+            (Selector::Slice(start, end, step), Value::Array(arr)) => selected.extend(
+                slice(arr.len(), *start, *end, *step)
+                    .into_iter()
+                    .map(|i| node.child(PathSegment::Index(i))),
+            ),
+            (Selector::Filter(f), _) => selected.extend(
+                node.children()
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter(|c| test(f, c, root)),
+            ),
+            _ => {}
+        }
+    }
+
+    selected
+}
+
+/// Indices of the items of an array of the given length selected by a slice.
+fn slice(len: usize, start: Option<i64>, end: Option<i64>, step: Option<i64>) -> Vec<usize> {
+    let len = len as i64;
+    let step = step.unwrap_or(1);
+    let normalize = |i: i64| if i >= 0 { i } else { len + i };
+
+    let mut indices = vec![];
+
+    if step > 0 {
+        let lower = normalize(start.unwrap_or(0)).clamp(0, len);
+        let upper = normalize(end.unwrap_or(len)).clamp(0, len);
+
+        let mut i = lower;
+        while i < upper {
+            indices.push(i as usize);
+            i = match i.checked_add(step) {
+                Some(i) => i,
+                None => break,
+            };
+        }
+    } else if step < 0 {
+        let upper = normalize(start.unwrap_or(len - 1)).clamp(-1, len - 1);
+        let lower = normalize(end.unwrap_or(-len - 1)).clamp(-1, len - 1);
+
+        let mut i = upper;
+        while lower < i {
+            indices.push(i as usize);
+            i = match i.checked_add(step) {
+                Some(i) => i,
+                None => break,
+            };
+        }
+    }
+
+    indices
+}
+
+fn test<'a>(filter: &Filter, current: &Output<'a>, root: &Output<'a>) -> bool {
+    match filter {
+        Filter::Exists(path) => !eval_path(path, current, root).is_empty(),
+        Filter::Compare(lhs, op, rhs) => {
+            let lhs = operand(lhs, current, root);
+            let rhs = operand(rhs, current, root);
+
+            let (lhs, rhs) = (lhs.as_ref(), rhs.as_ref());
+            match op {
+                CmpOp::Eq => equal(lhs, rhs),
+                CmpOp::Ne => !equal(lhs, rhs),
+                CmpOp::Lt => less(lhs, rhs),
+                CmpOp::Le => less(lhs, rhs) || equal(lhs, rhs),
+                CmpOp::Gt => less(rhs, lhs),
+                CmpOp::Ge => less(rhs, lhs) || equal(lhs, rhs),
+            }
+        }
+        Filter::And(lhs, rhs) => test(lhs, current, root) && test(rhs, current, root),
+        Filter::Or(lhs, rhs) => test(lhs, current, root) || test(rhs, current, root),
+        Filter::Not(f) => !test(f, current, root),
+    }
+}
+
+fn eval_path<'a>(path: &RelativePath, current: &Output<'a>, root: &Output<'a>) -> Vec<Output<'a>> {
+    let start = if path.absolute { root } else { current };
+
+    select(&path.segments, vec![start.clone()], root)
+}
+
+/// The value of an operand of a comparison, paths have a value only if they
+/// match exactly one node.
+fn operand<'a>(operand: &Operand, current: &Output<'a>, root: &Output<'a>) -> Option<Value> {
+    match operand {
+        Operand::Literal(v) => Some(v.clone()),
+        Operand::Path(path) => {
+            let mut nodes = eval_path(path, current, root);
+            if nodes.len() == 1 {
+                nodes.pop().map(|n| n.value.into_owned())
+            } else {
+                None
+            }
+        }
+    }
+}
+
+fn equal(a: Option<&Value>, b: Option<&Value>) -> bool {
+    match (a, b) {
+        (None, None) => true,
+        (Some(Value::Number(x)), Some(Value::Number(y))) => x.as_f64() == y.as_f64(),
+        (Some(x), Some(y)) => x == y,
+        _ => false,
+    }
+}
+
+fn less(a: Option<&Value>, b: Option<&Value>) -> bool {
+    match (a, b) {
+        (Some(Value::Number(x)), Some(Value::Number(y))) => x.as_f64() < y.as_f64(),
+        (Some(Value::String(x)), Some(Value::String(y))) => x < y,
+        _ => false,
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).cloned()
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn eat_str(&mut self, s: &str) -> bool {
+        let matches = s
+            .chars()
+            .enumerate()
+            .all(|(i, c)| self.chars.get(self.pos + i) == Some(&c));
+
+        if matches {
+            self.pos += s.chars().count();
+        }
+
+        matches
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        match self.peek() {
+            Some(n) if n == c => {
+                self.pos += 1;
+                Ok(())
+            }
+            Some(n) => Err(format!("expected '{}' but found '{}'", c, n)),
+            None => Err(format!("expected '{}' but the path ended", c)),
+        }
+    }
+
+    fn skip_ws(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.pos += 1;
+        }
+    }
+
+    fn parse_segments(&mut self) -> Result<Vec<Segment>, String> {
+        let mut segments = vec![];
+
+        loop {
+            let segment = if self.eat_str("..") {
+                if self.peek() == Some('[') {
+                    Segment::Descendant(self.parse_bracket()?)
+                } else if self.eat('*') {
+                    Segment::Descendant(vec![Selector::Wildcard])
+                } else {
+                    Segment::Descendant(vec![Selector::Name(self.parse_name()?)])
+                }
+            } else if self.eat('.') {
+                if self.eat('*') {
+                    Segment::Child(vec![Selector::Wildcard])
+                } else {
+                    Segment::Child(vec![Selector::Name(self.parse_name()?)])
+                }
+            } else if self.peek() == Some('[') {
+                Segment::Child(self.parse_bracket()?)
+            } else {
+                break;
+            };
+
+            segments.push(segment);
+        }
+
+        Ok(segments)
+    }
+
+    fn parse_name(&mut self) -> Result<String, String> {
+        let mut name = String::new();
+
+        while let Some(c) = self.peek() {
+            if !c.is_alphanumeric() && c != '_' && c != '-' {
+                break;
+            }
+
+            name.push(c);
+            self.pos += 1;
+        }
+
+        if name.is_empty() {
+            return Err("expected a name after '.'".to_string());
+        }
+
+        Ok(name)
+    }
+
+    fn parse_bracket(&mut self) -> Result<Vec<Selector>, String> {
+        self.expect('[')?;
+
+        let mut selectors = vec![self.parse_selector()?];
+        loop {
+            self.skip_ws();
+            if !self.eat(',') {
+                break;
+            }
+
+            selectors.push(self.parse_selector()?);
+        }
+
+        self.expect(']')?;
+
+        Ok(selectors)
+    }
+
+    fn parse_selector(&mut self) -> Result<Selector, String> {
+        self.skip_ws();
+
+        match self.peek() {
+            Some(q @ '\'') | Some(q @ '"') => {
+                self.pos += 1;
+                Ok(Selector::Name(self.parse_string(q)?))
+            }
+            Some('*') => {
+                self.pos += 1;
+                Ok(Selector::Wildcard)
+            }
+            Some('?') => {
+                self.pos += 1;
+                Ok(Selector::Filter(self.parse_or()?))
+            }
+            Some(c) if c == '-' || c == ':' || c.is_ascii_digit() => {
+                let start = self.parse_int()?;
+
+                self.skip_ws();
+                if !self.eat(':') {
+                    return start
+                        .map(Selector::Index)
+                        .ok_or_else(|| "expected an index".to_string());
+                }
+
+                let end = self.parse_int()?;
+                self.skip_ws();
+                let step = if self.eat(':') {
+                    self.parse_int()?
+                } else {
+                    None
+                };
+
+                Ok(Selector::Slice(start, end, step))
+            }
+            Some(c) => Err(format!("unexpected character '{}'", c)),
+            None => Err("unexpected end of the path".to_string()),
+        }
+    }
+
+    fn parse_int(&mut self) -> Result<Option<i64>, String> {
+        self.skip_ws();
+
+        let start = self.pos;
+        self.eat('-');
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+
+        if self.pos == start {
+            return Ok(None);
+        }
+
+        let n = self.chars[start..self.pos].iter().collect::<String>();
+        n.parse()
+            .map(Some)
+            .map_err(|_| format!("invalid integer {}", n))
+    }
+
+    /// Parse a string literal whose opening quote has already been consumed.
+    fn parse_string(&mut self, quote: char) -> Result<String, String> {
+        let mut s = String::new();
+
+        loop {
+            match self.peek() {
+                None => return Err("unterminated string".to_string()),
+                Some(c) if c == quote => {
+                    self.pos += 1;
+                    return Ok(s);
+                }
+                Some('\\') => {
+                    self.pos += 1;
+
+                    let c = match self.peek() {
+                        Some('n') => '\n',
+                        Some('t') => '\t',
+                        Some(c) => c,
+                        None => return Err("unterminated string".to_string()),
+                    };
+
+                    s.push(c);
+                    self.pos += 1;
+                }
+                Some(c) => {
+                    s.push(c);
+                    self.pos += 1;
+                }
+            }
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<Filter, String> {
+        let mut f = self.parse_and()?;
+
+        loop {
+            self.skip_ws();
+            if !self.eat_str("||") {
+                return Ok(f);
+            }
+
+            f = Filter::Or(Box::new(f), Box::new(self.parse_and()?));
+        }
+    }
+
+    fn parse_and(&mut self) -> Result<Filter, String> {
+        let mut f = self.parse_unary()?;
+
+        loop {
+            self.skip_ws();
+            if !self.eat_str("&&") {
+                return Ok(f);
+            }
+
+            f = Filter::And(Box::new(f), Box::new(self.parse_unary()?));
+        }
+    }
+
+    fn parse_unary(&mut self) -> Result<Filter, String> {
+        self.skip_ws();
+
+        if self.eat('!') {
+            return Ok(Filter::Not(Box::new(self.parse_unary()?)));
+        }
+
+        if self.eat('(') {
+            let f = self.parse_or()?;
+            self.skip_ws();
+            self.expect(')')?;
+            return Ok(f);
+        }
+
+        let lhs = self.parse_operand()?;
+
+        self.skip_ws();
+        let op = [
+            ("==", CmpOp::Eq),
+            ("!=", CmpOp::Ne),
+            ("<=", CmpOp::Le),
+            (">=", CmpOp::Ge),
+            ("<", CmpOp::Lt),
+            (">", CmpOp::Gt),
+        ]
+        .iter()
+        .find(|(s, _)| self.eat_str(s))
+        .map(|(_, op)| *op);
+
+        match (op, lhs) {
+            (Some(op), lhs) => Ok(Filter::Compare(lhs, op, self.parse_operand()?)),
+            (None, Operand::Path(path)) => Ok(Filter::Exists(path)),
+            (None, Operand::Literal(_)) => Err("expected a comparison".to_string()),
+        }
+    }
+
+    fn parse_operand(&mut self) -> Result<Operand, String> {
+        self.skip_ws();
+
+        match self.peek() {
+            Some(c @ '@') | Some(c @ '$') => {
+                self.pos += 1;
+
+                Ok(Operand::Path(RelativePath {
+                    absolute: c == '$',
+                    segments: self.parse_segments()?,
+                }))
+            }
+            Some(q @ '\'') | Some(q @ '"') => {
+                self.pos += 1;
+                Ok(Operand::Literal(Value::String(self.parse_string(q)?)))
+            }
+            Some(c) if c == '-' || c.is_ascii_digit() => {
+                let start = self.pos;
+                self.pos += 1;
+                while self
+                    .peek()
+                    .is_some_and(|c| c.is_ascii_digit() || matches!(c, '.' | 'e' | 'E' | '+' | '-'))
+                {
+                    self.pos += 1;
+                }
+
+                let n = self.chars[start..self.pos].iter().collect::<String>();
+                serde_json::from_str(&n)
+                    .map(Operand::Literal)
+                    .map_err(|_| format!("invalid number {}", n))
+            }
+            _ if self.eat_str("true") => Ok(Operand::Literal(Value::Bool(true))),
+            _ if self.eat_str("false") => Ok(Operand::Literal(Value::Bool(false))),
+            _ if self.eat_str("null") => Ok(Operand::Literal(Value::Null)),
+            Some(c) => Err(format!("unexpected character '{}'", c)),
+            None => Err("unexpected end of the path".to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::JsonPath;
+    use serde_json::json;
+
+    fn refs(path: &str, value: &serde_json::Value) -> Vec<String> {
+        path.parse::<JsonPath>()
+            .unwrap()
+            .run(value)
+            .into_iter()
+            .map(|o| o.reference().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_jsonpath() {
+        let doc = json!({
+            "name": "root",
+            "items": [
+                {"name": "a", "age": 20},
+                {"name": "b", "age": 40, "tags": {"name": "t"}},
+                {"name": "c", "age": 35}
+            ]
+        });
+
+        assert_eq!(refs("$", &doc), vec!["#"]);
+        assert_eq!(refs("$.items[1].name", &doc), vec!["#/items/1/name"]);
+        assert_eq!(refs("$['items'][-1]['age']", &doc), vec!["#/items/2/age"]);
+        assert_eq!(
+            refs("$..name", &doc),
+            vec![
+                "#/name",
+                "#/items/0/name",
+                "#/items/1/name",
+                "#/items/1/tags/name",
+                "#/items/2/name",
+            ]
+        );
+        assert_eq!(
+            refs("$.items[*].age", &doc),
+            vec!["#/items/0/age", "#/items/1/age", "#/items/2/age"]
+        );
+        assert_eq!(
+            refs("$.items[?(@.age > 30)]", &doc),
+            vec!["#/items/1", "#/items/2"]
+        );
+        assert_eq!(
+            refs("$.items[?(@.tags && @.name != 'c')].name", &doc),
+            vec!["#/items/1/name"]
+        );
+        assert_eq!(
+            refs("$.items[?@.name == $.items[0].name]", &doc),
+            vec!["#/items/0"]
+        );
+        assert_eq!(refs("$.items[?(!@.tags)].name", &doc).len(), 2);
+        assert_eq!(refs("$.missing", &doc), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_slices() {
+        let doc = json!([0, 1, 2, 3, 4, 5]);
+
+        assert_eq!(refs("$[1:3]", &doc), vec!["#/1", "#/2"]);
+        assert_eq!(refs("$[:2]", &doc), vec!["#/0", "#/1"]);
+        assert_eq!(refs("$[-2:]", &doc), vec!["#/4", "#/5"]);
+        assert_eq!(refs("$[::2]", &doc), vec!["#/0", "#/2", "#/4"]);
+        assert_eq!(refs("$[::-2]", &doc), vec!["#/5", "#/3", "#/1"]);
+        assert_eq!(refs("$[0, 5]", &doc), vec!["#/0", "#/5"]);
+        assert_eq!(refs("$[4:1]", &doc), Vec::<String>::new());
+        assert_eq!(refs("$[1::9223372036854775807]", &doc), vec!["#/1"]);
+        assert_eq!(refs("$[::-9223372036854775808]", &doc), vec!["#/5"]);
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!("items".parse::<JsonPath>().is_err());
+        assert!("$.".parse::<JsonPath>().is_err());
+        assert!("$[".parse::<JsonPath>().is_err());
+        assert!("$['a'".parse::<JsonPath>().is_err());
+        assert!("$[?(@.a > )]".parse::<JsonPath>().is_err());
+        assert!("$[?(42)]".parse::<JsonPath>().is_err());
+    }
+}
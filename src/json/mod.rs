@@ -1,11 +1,21 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+
 use termion::color;
 use termion::style;
 
-use crate::widgets::ascii_line::AsciiLine;
+use crate::json::container::containers;
+use crate::json::index::{index, Index};
+use crate::widgets::unicode_line::UnicodeLine;
 use crate::widgets::view::Line;
 
+pub mod completion;
+pub mod container;
 pub mod index;
+pub mod jq;
+pub mod jsonpath;
 mod parser;
+pub mod pointer;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct JsonLine {
@@ -15,7 +25,7 @@ pub struct JsonLine {
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct JsonToken {
     tag: JsonTokenTag,
-    text: AsciiLine<String>,
+    text: UnicodeLine<String>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -33,10 +43,107 @@ pub enum JsonTokenTag {
     ObjectKey,
     Whitespace,
     Ref,
+
+    /// A date, a time or both, like the datetimes of TOML. They're strings
+    /// in JSON.
+    DateTime,
+
+    /// Preview of a binary blob, it's a base64 string in JSON.
+    Binary,
+
+    /// Name of the type a value has in its original format, like `ObjectId`
+    /// in BSON, shown before the value.
+    TypeName,
+
+    /// Reference of the value another one is a copy of, like the anchor of a
+    /// YAML alias, shown before the copy. It's followed like the `Ref`s, but
+    /// it isn't a value itself.
+    Alias,
+
+    /// A comment of a format that has them, like JSON5.
+    Comment,
+
+    /// Raw text that isn't valid JSON.
+    Invalid,
+
+    /// Description of why the text before it isn't valid JSON.
+    Error,
 }
 
-pub fn parse_json(json: serde_json::Value) -> Result<Vec<JsonLine>, String> {
-    parser::parse_json_lines(json, 0)
+/// How to show a value converted from a format with types that JSON doesn't
+/// have, like a BSON ObjectId, a CBOR tagged value or a TOML datetime, or with
+/// values that are copies of others, like the YAML aliases.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Annotation {
+    /// Pointer of the value, like `/items/0/id`.
+    pub pointer: String,
+
+    /// Tag of the token of the value instead of the JSON one, e.g. `DateTime`.
+    pub tag: Option<JsonTokenTag>,
+
+    /// Text shown instead of the value, e.g. the preview of a binary blob.
+    pub text: Option<String>,
+
+    /// Name of the original type of the value, e.g. `ObjectId`.
+    pub type_name: Option<String>,
+
+    /// Reference of the value this one is a copy of, e.g. `#/defaults` for
+    /// the YAML alias `*defaults`.
+    pub alias: Option<String>,
+}
+
+/// Where a comment is shown relative to the value it belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommentPosition {
+    /// On its own line before the value.
+    Before,
+
+    /// At the end of the line the value starts on, e.g. after an opening
+    /// bracket.
+    Start,
+
+    /// At the end of the line the value ends on.
+    End,
+
+    /// On its own line before the closing bracket of the value.
+    BeforeEnd,
+
+    /// On its own line after the value.
+    After,
+}
+
+/// A comment of the original document, it's kept next to its value whatever
+/// the order of the keys.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Comment {
+    /// Pointer of the value, like `/compilerOptions/strict`.
+    pub pointer: String,
+    pub position: CommentPosition,
+
+    /// Text of the comment along with its delimiters, like `// strict mode`.
+    pub text: String,
+}
+
+/// The order the keys of the objects are shown in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyOrder {
+    /// Alphabetical order.
+    Sorted,
+
+    /// The order the keys appear in the original document.
+    Document,
+}
+
+pub fn parse_json(json: serde_json::Value, order: KeyOrder) -> Vec<JsonLine> {
+    parser::parse_json_lines(json, 0, order)
+}
+
+/// Parse newline delimited JSON where every non empty line is a record. The
+/// records are returned along with the lines, but the lines that are not valid
+/// JSON are shown as invalid followed by the error and their record is their
+/// raw text.
+pub fn parse_ndjson(input: &str, order: KeyOrder) -> (Vec<JsonLine>, Vec<serde_json::Value>) {
+    parser::parse_ndjson_lines(input, order)
 }
 
 impl JsonLine {
@@ -59,102 +166,262 @@ impl JsonLine {
 
         None
     }
+
+    fn token_ix_at(&self, idx: usize) -> Option<usize> {
+        let mut col = 0;
+
+        for (i, t) in self.tokens.iter().enumerate() {
+            col += t.chars_count();
+
+            if idx < col {
+                return Some(i);
+            }
+        }
+
+        None
+    }
+}
+
+/// Apply the annotations to the values of the given lines, which are found via
+/// their index. The lines must be indexed again afterwards since the tokens
+/// might change width.
+pub fn annotate(lines: &mut [JsonLine], index: &Index, annotations: Vec<Annotation>) {
+    let mut annotations = annotations
+        .into_iter()
+        .filter_map(|a| Some((*index.get(&format!("#{}", a.pointer))?, a)))
+        .collect::<Vec<_>>();
+
+    // annotating a token moves the ones after it so start from the last one,
+    // the type names and the aliases go before the value so they're added last
+    annotations.sort_by_key(|((r, c), a)| {
+        (
+            std::cmp::Reverse((*r, *c)),
+            a.type_name.is_some() || a.alias.is_some(),
+        )
+    });
+
+    for ((r, c), a) in annotations {
+        let line = &mut lines[r];
+        let i = match line.token_ix_at(c) {
+            Some(i) => i,
+            None => continue,
+        };
+
+        if let Some(tag) = a.tag {
+            line.tokens[i].tag = tag;
+        }
+
+        if let Some(text) = a.text {
+            line.tokens[i].text = UnicodeLine::new(text);
+        }
+
+        if let Some(type_name) = a.type_name {
+            line.tokens.insert(
+                i,
+                JsonToken {
+                    tag: JsonTokenTag::TypeName,
+                    text: UnicodeLine::new(format!("{} ", type_name)),
+                },
+            );
+        }
+
+        if let Some(alias) = a.alias {
+            line.tokens.insert(i, JsonToken::ws(1));
+            line.tokens.insert(
+                i,
+                JsonToken {
+                    tag: JsonTokenTag::Alias,
+                    text: UnicodeLine::new(quote(&alias)),
+                },
+            );
+        }
+    }
+}
+
+/// Add the comments to the lines of their values, either as new lines or at
+/// the end of the existing ones. The lines must be indexed again afterwards.
+pub fn add_comments(lines: &mut Vec<JsonLine>, comments: Vec<Comment>) {
+    if comments.is_empty() {
+        return;
+    }
+
+    let (index, _) = index(lines);
+    let ends = containers(lines)
+        .into_iter()
+        .map(|c| (c.start, c.end.0))
+        .collect::<HashMap<_, _>>();
+
+    let indent = |line: &JsonLine| match line.tokens.first() {
+        Some(t) if t.tag == JsonTokenTag::Whitespace => t.chars_count(),
+        _ => 0,
+    };
+
+    // the new lines are inserted at the end so that the rows stay valid
+    let mut appends = vec![];
+    let mut inserts = vec![];
+
+    for comment in comments {
+        let (r, c) = match index.get(&format!("#{}", comment.pointer)) {
+            Some(&pos) => pos,
+            None => continue,
+        };
+        let end = ends.get(&(r, c)).copied().unwrap_or(r);
+
+        match comment.position {
+            CommentPosition::Before => inserts.push((r, indent(&lines[r]), comment.text)),
+            CommentPosition::Start => appends.push((r, comment.text)),
+            CommentPosition::End => appends.push((end, comment.text)),
+            CommentPosition::BeforeEnd => {
+                inserts.push((end, indent(&lines[end]) + 4, comment.text));
+            }
+            CommentPosition::After => inserts.push((end + 1, indent(&lines[end]), comment.text)),
+        }
+    }
+
+    for (r, text) in appends {
+        // a multiline comment at the end of a line is squashed on it
+        let text = text.lines().map(str::trim).collect::<Vec<_>>().join(" ");
+
+        lines[r].tokens.push(JsonToken::ws(1));
+        lines[r].tokens.push(JsonToken::comment(text));
+    }
+
+    // the comments inserted at the same row are inserted in reverse so that
+    // they keep their order
+    inserts.sort_by_key(|(r, _, _)| *r);
+    for (r, indent, text) in inserts.into_iter().rev() {
+        let comment_lines = text.lines().enumerate().map(|(i, l)| {
+            // the continuation lines of a block comment are aligned to its
+            // start, with the leading stars one column to the right
+            let l = if i == 0 { l } else { l.trim() };
+            let indent = if i > 0 && l.starts_with('*') {
+                indent + 1
+            } else {
+                indent
+            };
+
+            let mut tokens = vec![];
+            if indent > 0 {
+                tokens.push(JsonToken::ws(indent));
+            }
+            tokens.push(JsonToken::comment(l.to_string()));
+
+            JsonLine::new(tokens)
+        });
+
+        lines.splice(r..r, comment_lines.collect::<Vec<_>>());
+    }
 }
 
 impl JsonToken {
     pub fn ws(s: usize) -> Self {
         JsonToken {
             tag: JsonTokenTag::Whitespace,
-            text: AsciiLine::new((0..s).map(|_| ' ').collect()).unwrap(),
+            text: UnicodeLine::new((0..s).map(|_| ' ').collect()),
         }
     }
 
     pub fn bool(b: bool) -> Self {
         JsonToken {
             tag: JsonTokenTag::Bool,
-            text: AsciiLine::new(b.to_string()).unwrap(),
+            text: UnicodeLine::new(b.to_string()),
         }
     }
 
     pub fn null() -> Self {
         JsonToken {
             tag: JsonTokenTag::Null,
-            text: AsciiLine::new("null".to_string()).unwrap(),
+            text: UnicodeLine::new("null".to_string()),
         }
     }
 
     pub fn number(n: serde_json::Number) -> Self {
         JsonToken {
             tag: JsonTokenTag::Number,
-            text: AsciiLine::new(n.to_string()).unwrap(),
+            text: UnicodeLine::new(n.to_string()),
         }
     }
 
-    pub fn string(mut s: String) -> Result<JsonToken, String> {
-        let tag = if s.starts_with("#/") {
+    pub fn string(s: String) -> Self {
+        let tag = if pointer::is_reference(&s) {
             JsonTokenTag::Ref
         } else {
             JsonTokenTag::String
         };
 
-        s.insert(0, '"');
-        s.push('"');
-
-        Ok(JsonToken {
+        JsonToken {
             tag,
-            text: AsciiLine::new(s.to_string())?,
-        })
+            text: UnicodeLine::new(quote(&s)),
+        }
     }
 
-    pub fn object_key(mut s: String) -> Result<JsonToken, String> {
-        s.insert(0, '"');
-        s.push('"');
-
-        Ok(JsonToken {
+    pub fn object_key(s: String) -> Self {
+        JsonToken {
             tag: JsonTokenTag::ObjectKey,
-            text: AsciiLine::new(s.to_string())?,
-        })
+            text: UnicodeLine::new(quote(&s)),
+        }
+    }
+
+    pub fn comment(s: String) -> Self {
+        JsonToken {
+            tag: JsonTokenTag::Comment,
+            text: UnicodeLine::new(s),
+        }
+    }
+
+    pub fn invalid(s: String) -> Self {
+        JsonToken {
+            tag: JsonTokenTag::Invalid,
+            text: UnicodeLine::new(s),
+        }
+    }
+
+    pub fn error(e: &serde_json::Error) -> Self {
+        JsonToken {
+            tag: JsonTokenTag::Error,
+            text: UnicodeLine::new(format!("✗ {}", e)),
+        }
     }
 
     pub fn array_start() -> Self {
         JsonToken {
             tag: JsonTokenTag::ArrayStart,
-            text: AsciiLine::new('['.to_string()).unwrap(),
+            text: UnicodeLine::new('['.to_string()),
         }
     }
 
     pub fn array_end() -> Self {
         JsonToken {
             tag: JsonTokenTag::ArrayEnd,
-            text: AsciiLine::new(']'.to_string()).unwrap(),
+            text: UnicodeLine::new(']'.to_string()),
         }
     }
 
     pub fn object_start() -> Self {
         JsonToken {
             tag: JsonTokenTag::ObjectStart,
-            text: AsciiLine::new('{'.to_string()).unwrap(),
+            text: UnicodeLine::new('{'.to_string()),
         }
     }
 
     pub fn object_end() -> Self {
         JsonToken {
             tag: JsonTokenTag::ObjectEnd,
-            text: AsciiLine::new('}'.to_string()).unwrap(),
+            text: UnicodeLine::new('}'.to_string()),
         }
     }
 
     pub fn comma() -> Self {
         JsonToken {
             tag: JsonTokenTag::Comma,
-            text: AsciiLine::new(','.to_string()).unwrap(),
+            text: UnicodeLine::new(','.to_string()),
         }
     }
 
     pub fn colon() -> Self {
         JsonToken {
             tag: JsonTokenTag::Colon,
-            text: AsciiLine::new(':'.to_string()).unwrap(),
+            text: UnicodeLine::new(':'.to_string()),
         }
     }
 
@@ -200,25 +467,54 @@ impl Line for JsonLine {
     fn render(&self, start_col: usize, width: usize) -> String {
         let mut l = String::new();
         let mut col = 0;
+        let mut width_left = width;
 
         for t in &self.tokens {
             let c = t.chars_count();
 
             if start_col < col + c {
                 let s = start_col.saturating_sub(col);
-                let w = start_col + width - col;
 
-                l.push_str(&t.render(s, w));
+                l.push_str(&t.render(s, width_left));
+
+                // stop as soon as a character doesn't fit, otherwise the
+                // following tokens might be rendered in the wrong place.
+                for i in s..c {
+                    let w = usize::from(t.char_width(i));
+                    if w > width_left {
+                        return l;
+                    }
+
+                    width_left -= w;
+                }
             }
 
             col += c;
+        }
+
+        l
+    }
+
+    fn text(&self) -> Cow<'_, str> {
+        Cow::Owned(self.tokens.iter().map(|t| t.text()).collect())
+    }
 
-            if col >= start_col + width {
-                break;
+    // every token is a word, so that `w` jumps over a whole string or number
+    fn words(&self) -> Vec<(usize, usize)> {
+        let mut words = vec![];
+        let mut col = 0;
+
+        for t in &self.tokens {
+            let c = t.chars_count();
+
+            if t.tag != JsonTokenTag::Whitespace && c > 0 {
+                words.push((col, col + c));
             }
+
+            col += c;
         }
 
-        l
+        words
     }
 }
 
@@ -235,6 +531,10 @@ impl Line for JsonToken {
         self.text.indent(width);
     }
 
+    fn text(&self) -> Cow<'_, str> {
+        self.text.text()
+    }
+
     fn render(&self, start_col: usize, width: usize) -> String {
         // termion colors are different types, that's annoying...
         match self.tag {
@@ -293,6 +593,28 @@ impl Line for JsonToken {
                 color::Fg(color::Yellow),
                 self.text.render(start_col, width)
             ),
+            JsonTokenTag::DateTime => format!(
+                "{}{}",
+                color::Fg(color::LightBlue),
+                self.text.render(start_col, width)
+            ),
+            JsonTokenTag::Binary => format!(
+                "{}{}",
+                color::Fg(color::Green),
+                self.text.render(start_col, width)
+            ),
+            JsonTokenTag::TypeName => format!(
+                "{}{}{}{}",
+                color::Fg(color::LightBlack),
+                style::Italic,
+                self.text.render(start_col, width),
+                style::NoItalic,
+            ),
+            JsonTokenTag::Comment => format!(
+                "{}{}",
+                color::Fg(color::LightBlack),
+                self.text.render(start_col, width)
+            ),
             JsonTokenTag::Ref => format!(
                 "{}{}{}{}",
                 color::Fg(color::Yellow),
@@ -300,6 +622,27 @@ impl Line for JsonToken {
                 self.text.render(start_col, width),
                 style::NoUnderline,
             ),
+            JsonTokenTag::Alias => format!(
+                "{}{}{}{}{}{}",
+                color::Fg(color::LightBlack),
+                style::Italic,
+                style::Underline,
+                self.text.render(start_col, width),
+                style::NoUnderline,
+                style::NoItalic,
+            ),
+            JsonTokenTag::Invalid => format!(
+                "{}{}",
+                color::Fg(color::LightRed),
+                self.text.render(start_col, width)
+            ),
+            JsonTokenTag::Error => format!(
+                "{}{}{}{}",
+                color::Fg(color::Red),
+                style::Italic,
+                self.text.render(start_col, width),
+                style::NoItalic,
+            ),
             JsonTokenTag::ObjectKey => format!(
                 "{}{}",
                 color::Fg(color::Cyan),
@@ -308,3 +651,15 @@ impl Line for JsonToken {
         }
     }
 }
+
+/// Quote the given string escaping it like JSON does, so that quotes and
+/// newlines don't break the line it's shown on.
+fn quote(s: &str) -> String {
+    serde_json::Value::String(s.to_string()).to_string()
+}
+
+/// The string whose quoted text is the given one, i.e. the reverse of what
+/// `JsonToken::string` and `JsonToken::object_key` do.
+pub fn unquote(text: &str) -> Option<String> {
+    serde_json::from_str(text).ok()
+}
@@ -1,8 +1,8 @@
 use serde_json;
 
-use crate::json::{JsonLine, JsonToken};
+use crate::json::{JsonLine, JsonToken, KeyOrder};
 
-pub fn parse_json_lines(json: serde_json::Value, indent: usize) -> Result<Vec<JsonLine>, String> {
+pub fn parse_json_lines(json: serde_json::Value, indent: usize, order: KeyOrder) -> Vec<JsonLine> {
     use serde_json::Value;
 
     let mut lines = vec![];
@@ -19,7 +19,7 @@ pub fn parse_json_lines(json: serde_json::Value, indent: usize) -> Result<Vec<Js
         }),
         Value::String(s) => {
             lines.push(JsonLine {
-                tokens: vec![JsonToken::string(s)?],
+                tokens: vec![JsonToken::string(s)],
             });
         }
         Value::Array(ref arr) if arr.is_empty() => {
@@ -34,7 +34,7 @@ pub fn parse_json_lines(json: serde_json::Value, indent: usize) -> Result<Vec<Js
 
             let arr_len = arr.len();
             for (i, v) in arr.into_iter().enumerate() {
-                let mut children = parse_json_lines(v, indent + 4)?;
+                let mut children = parse_json_lines(v, indent + 4, order);
 
                 if i < arr_len - 1 {
                     children.last_mut().unwrap().tokens.push(JsonToken::comma());
@@ -67,15 +67,17 @@ pub fn parse_json_lines(json: serde_json::Value, indent: usize) -> Result<Vec<Js
             // this is potentially inefficient for large objects but it's pretty
             // useful
             let mut items = obj.into_iter().collect::<Vec<_>>();
-            items.sort_by(|o1, o2| (o1.0).cmp(&o2.0));
+            if order == KeyOrder::Sorted {
+                items.sort_by(|o1, o2| (o1.0).cmp(&o2.0));
+            }
 
             for (i, (k, v)) in items.into_iter().enumerate() {
-                let mut children = parse_json_lines(v, indent + 4)?;
+                let mut children = parse_json_lines(v, indent + 4, order);
 
                 children[0].tokens.insert(0, JsonToken::ws(1));
                 children[0].tokens.insert(0, JsonToken::colon());
 
-                children[0].tokens.insert(0, JsonToken::object_key(k)?);
+                children[0].tokens.insert(0, JsonToken::object_key(k));
 
                 children[0].tokens.insert(0, JsonToken::ws(indent + 4));
 
@@ -96,12 +98,38 @@ pub fn parse_json_lines(json: serde_json::Value, indent: usize) -> Result<Vec<Js
         }
     };
 
-    Ok(lines)
+    lines
+}
+
+pub fn parse_ndjson_lines(input: &str, order: KeyOrder) -> (Vec<JsonLine>, Vec<serde_json::Value>) {
+    let mut lines = vec![];
+    let mut records = vec![];
+
+    for l in input.lines().filter(|l| !l.trim().is_empty()) {
+        match serde_json::from_str::<serde_json::Value>(l) {
+            Ok(value) => {
+                lines.extend(parse_json_lines(value.clone(), 0, order));
+                records.push(value);
+            }
+            Err(e) => {
+                lines.push(JsonLine::new(vec![
+                    JsonToken::invalid(l.to_string()),
+                    JsonToken::ws(2),
+                    JsonToken::error(&e),
+                ]));
+
+                // the record is still there, it's just not JSON
+                records.push(serde_json::Value::String(l.to_string()));
+            }
+        }
+    }
+
+    (lines, records)
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::json::{JsonLine, JsonToken};
+    use crate::json::{JsonLine, JsonToken, KeyOrder};
 
     #[test]
     fn test_parse_json_primitive() {
@@ -118,7 +146,7 @@ mod tests {
             JsonLine::new(vec![JsonToken::object_start()]),
             JsonLine::new(vec![
                 JsonToken::ws(4),
-                JsonToken::object_key("empty-array".to_string()).unwrap(),
+                JsonToken::object_key("empty-array".to_string()),
                 JsonToken::colon(),
                 JsonToken::ws(1),
                 JsonToken::array_start(),
@@ -127,7 +155,7 @@ mod tests {
             ]),
             JsonLine::new(vec![
                 JsonToken::ws(4),
-                JsonToken::object_key("empty-object".to_string()).unwrap(),
+                JsonToken::object_key("empty-object".to_string()),
                 JsonToken::colon(),
                 JsonToken::ws(1),
                 JsonToken::object_start(),
@@ -136,15 +164,15 @@ mod tests {
             ]),
             JsonLine::new(vec![
                 JsonToken::ws(4),
-                JsonToken::object_key("name".to_string()).unwrap(),
+                JsonToken::object_key("name".to_string()),
                 JsonToken::colon(),
                 JsonToken::ws(1),
-                JsonToken::string("mattors".to_string()).unwrap(),
+                JsonToken::string("mattors".to_string()),
                 JsonToken::comma(),
             ]),
             JsonLine::new(vec![
                 JsonToken::ws(4),
-                JsonToken::object_key("private".to_string()).unwrap(),
+                JsonToken::object_key("private".to_string()),
                 JsonToken::colon(),
                 JsonToken::ws(1),
                 JsonToken::bool(true),
@@ -152,19 +180,19 @@ mod tests {
             ]),
             JsonLine::new(vec![
                 JsonToken::ws(4),
-                JsonToken::object_key("ref1".to_string()).unwrap(),
+                JsonToken::object_key("ref1".to_string()),
                 JsonToken::colon(),
                 JsonToken::ws(1),
-                JsonToken::string("#/ciaomondo/23".to_string()).unwrap(),
+                JsonToken::string("#/ciaomondo/23".to_string()),
             ]),
             JsonLine::new(vec![JsonToken::object_end()]),
         ];
 
         let value = serde_json::from_str(input_json).unwrap();
-        let lines = super::parse_json_lines(value, 0).unwrap();
+        let lines = super::parse_json_lines(value, 0, KeyOrder::Sorted);
 
         assert_eq!(lines.len(), expected.len());
-        for (i, (g, e)) in lines.into_iter().zip(expected.into_iter()).enumerate() {
+        for (i, (g, e)) in lines.into_iter().zip(expected).enumerate() {
             assert_eq!(g, e, "line #{} differ", i);
         }
     }
@@ -190,10 +218,10 @@ mod tests {
         ];
 
         let value = serde_json::from_str(input_json).unwrap();
-        let lines = super::parse_json_lines(value, 0).unwrap();
+        let lines = super::parse_json_lines(value, 0, KeyOrder::Sorted);
 
         assert_eq!(lines.len(), expected.len());
-        for (i, (g, e)) in lines.into_iter().zip(expected.into_iter()).enumerate() {
+        for (i, (g, e)) in lines.into_iter().zip(expected).enumerate() {
             assert_eq!(g, e, "line #{} differ", i);
         }
     }
@@ -206,7 +234,7 @@ mod tests {
             JsonLine::new(vec![JsonToken::object_start()]),
             JsonLine::new(vec![
                 JsonToken::ws(4),
-                JsonToken::object_key("a".to_string()).unwrap(),
+                JsonToken::object_key("a".to_string()),
                 JsonToken::colon(),
                 JsonToken::ws(1),
                 JsonToken::array_start(),
@@ -229,7 +257,7 @@ mod tests {
             JsonLine::new(vec![JsonToken::ws(8), JsonToken::object_start()]),
             JsonLine::new(vec![
                 JsonToken::ws(12),
-                JsonToken::object_key("hello-world".to_string()).unwrap(),
+                JsonToken::object_key("hello-world".to_string()),
                 JsonToken::colon(),
                 JsonToken::ws(1),
                 JsonToken::null(),
@@ -240,11 +268,98 @@ mod tests {
         ];
 
         let value = serde_json::from_str(input_json).unwrap();
-        let lines = super::parse_json_lines(value, 0).unwrap();
+        let lines = super::parse_json_lines(value, 0, KeyOrder::Sorted);
 
         assert_eq!(lines.len(), expected.len());
-        for (i, (g, e)) in lines.into_iter().zip(expected.into_iter()).enumerate() {
+        for (i, (g, e)) in lines.into_iter().zip(expected).enumerate() {
             assert_eq!(g, e, "line #{} differ", i);
         }
     }
+
+    #[test]
+    fn test_parse_json_document_order() {
+        let input_json = r#"{"b": 1, "a": {"d": null, "c": true}}"#;
+
+        let expected = vec![
+            JsonLine::new(vec![JsonToken::object_start()]),
+            JsonLine::new(vec![
+                JsonToken::ws(4),
+                JsonToken::object_key("b".to_string()),
+                JsonToken::colon(),
+                JsonToken::ws(1),
+                JsonToken::number(1.into()),
+                JsonToken::comma(),
+            ]),
+            JsonLine::new(vec![
+                JsonToken::ws(4),
+                JsonToken::object_key("a".to_string()),
+                JsonToken::colon(),
+                JsonToken::ws(1),
+                JsonToken::object_start(),
+            ]),
+            JsonLine::new(vec![
+                JsonToken::ws(8),
+                JsonToken::object_key("d".to_string()),
+                JsonToken::colon(),
+                JsonToken::ws(1),
+                JsonToken::null(),
+                JsonToken::comma(),
+            ]),
+            JsonLine::new(vec![
+                JsonToken::ws(8),
+                JsonToken::object_key("c".to_string()),
+                JsonToken::colon(),
+                JsonToken::ws(1),
+                JsonToken::bool(true),
+            ]),
+            JsonLine::new(vec![JsonToken::ws(4), JsonToken::object_end()]),
+            JsonLine::new(vec![JsonToken::object_end()]),
+        ];
+
+        let value = serde_json::from_str(input_json).unwrap();
+        let lines = super::parse_json_lines(value, 0, KeyOrder::Document);
+
+        assert_eq!(lines.len(), expected.len());
+        for (i, (g, e)) in lines.into_iter().zip(expected).enumerate() {
+            assert_eq!(g, e, "line #{} differ", i);
+        }
+    }
+
+    #[test]
+    fn test_parse_ndjson() {
+        let input = "{\"a\": 1}\n\n[true, \n42\n";
+        let (lines, records) = super::parse_ndjson_lines(input, KeyOrder::Sorted);
+
+        let err = serde_json::from_str::<serde_json::Value>("[true, ").unwrap_err();
+
+        assert_eq!(
+            lines,
+            vec![
+                JsonLine::new(vec![JsonToken::object_start()]),
+                JsonLine::new(vec![
+                    JsonToken::ws(4),
+                    JsonToken::object_key("a".to_string()),
+                    JsonToken::colon(),
+                    JsonToken::ws(1),
+                    JsonToken::number(1.into()),
+                ]),
+                JsonLine::new(vec![JsonToken::object_end()]),
+                JsonLine::new(vec![
+                    JsonToken::invalid("[true, ".to_string()),
+                    JsonToken::ws(2),
+                    JsonToken::error(&err),
+                ]),
+                JsonLine::new(vec![JsonToken::number(42.into())]),
+            ]
+        );
+
+        assert_eq!(
+            records,
+            vec![
+                serde_json::json!({"a": 1}),
+                serde_json::json!("[true, "),
+                serde_json::json!(42),
+            ]
+        );
+    }
 }
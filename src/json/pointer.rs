@@ -0,0 +1,100 @@
+//! JSON Pointers (RFC 6901) in their URI fragment representation, like
+//! `#/paths/~1users~1%7Bid%7D`, and the references made of them.
+
+/// Whether the given string is a reference to a value, either in the same
+/// document like `#/definitions/Id` or in another file relative to it like
+/// `common.json#/definitions/Id`.
+pub fn is_reference(s: &str) -> bool {
+    match s.split_once('#') {
+        Some(("", pointer)) => pointer.starts_with('/'),
+        Some((file, pointer)) => {
+            (pointer.is_empty() || pointer.starts_with('/'))
+                && !file.contains(char::is_whitespace)
+                && !file.contains("://")
+                && file.contains('.')
+        }
+        None => false,
+    }
+}
+
+/// Escape an object key so that it can be used as a reference token in a
+/// pointer, i.e. "~" becomes "~0" and "/" becomes "~1".
+pub fn escape(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+/// Get back the object key from a reference token of a pointer.
+pub fn unescape(token: &str) -> String {
+    token.replace("~1", "/").replace("~0", "~")
+}
+
+/// Decode the percent-encoded characters of a URI fragment. The fragment is
+/// returned as is if it doesn't decode to valid UTF-8.
+pub fn percent_decode(fragment: &str) -> String {
+    let bytes = fragment.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+
+    let mut i = 0;
+    while i < bytes.len() {
+        // from_str_radix accepts a sign too, e.g. "+1"
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .filter(|h| h.iter().all(u8::is_ascii_hexdigit))
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+
+        match (bytes[i], hex) {
+            (b'%', Some(b)) => {
+                decoded.push(b);
+                i += 3;
+            }
+            (b, _) => {
+                decoded.push(b);
+                i += 1;
+            }
+        }
+    }
+
+    String::from_utf8(decoded).unwrap_or_else(|_| fragment.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{escape, is_reference, percent_decode, unescape};
+
+    #[test]
+    fn test_is_reference() {
+        assert!(is_reference("#/definitions/Id"));
+        assert!(is_reference("common.json#/definitions/Id"));
+        assert!(is_reference("../schemas/common.json#"));
+        assert!(!is_reference("#"));
+        assert!(!is_reference("#hashtag"));
+        assert!(!is_reference("C#"));
+        assert!(!is_reference("see file.json#/a"));
+        assert!(!is_reference("https://example.com/schema.json#/a"));
+        assert!(!is_reference("common.json"));
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape("/users/{id}"), "~1users~1{id}");
+        assert_eq!(escape("a~/b"), "a~0~1b");
+        assert_eq!(unescape("~1users~1{id}"), "/users/{id}");
+        assert_eq!(unescape("~01"), "~1");
+        assert_eq!(unescape(&escape("~1/~0")), "~1/~0");
+    }
+
+    #[test]
+    fn test_percent_decode() {
+        assert_eq!(
+            percent_decode("#/paths/~1users~1%7Bid%7D"),
+            "#/paths/~1users~1{id}"
+        );
+        assert_eq!(percent_decode("#/caf%C3%A9"), "#/café");
+        assert_eq!(percent_decode("#/100%"), "#/100%");
+        assert_eq!(percent_decode("#/%zz"), "#/%zz");
+        assert_eq!(percent_decode("#/%+1"), "#/%+1");
+        assert_eq!(percent_decode("#/%-1"), "#/%-1");
+        assert_eq!(percent_decode("#/%FF"), "#/%FF");
+    }
+}
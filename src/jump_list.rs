@@ -0,0 +1,109 @@
+/// Maximum number of positions remembered, the oldest ones are dropped first.
+const MAX_JUMPS: usize = 100;
+
+/// The (row, col) positions the cursor jumped away from, like vim's jump list,
+/// to go back and forth between them.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct JumpList {
+    jumps: Vec<(usize, usize)>,
+
+    // index of the jump the cursor is at while going through the list, it's
+    // jumps.len() when the cursor is not on any of them
+    current: usize,
+}
+
+impl JumpList {
+    /// Remember the position the cursor is about to jump away from. The jumps
+    /// that were gone back from are forgotten.
+    pub fn push(&mut self, pos: (usize, usize)) {
+        self.jumps.truncate(self.current);
+        self.jumps.retain(|p| *p != pos);
+        self.jumps.push(pos);
+
+        if self.jumps.len() > MAX_JUMPS {
+            self.jumps.remove(0);
+        }
+
+        self.current = self.jumps.len();
+    }
+
+    /// Go back to the previous jump position given the current position of the
+    /// cursor, which is remembered to be able to go forward to it again.
+    pub fn back(&mut self, pos: (usize, usize)) -> Option<(usize, usize)> {
+        if self.current == self.jumps.len() {
+            self.push(pos);
+            self.current -= 1;
+        }
+
+        self.current = self.current.checked_sub(1)?;
+        Some(self.jumps[self.current])
+    }
+
+    /// Go forward to the jump position that was gone back from.
+    pub fn forward(&mut self) -> Option<(usize, usize)> {
+        if self.current + 1 >= self.jumps.len() {
+            return None;
+        }
+
+        self.current += 1;
+        Some(self.jumps[self.current])
+    }
+
+    /// All the jump positions from the oldest to the newest.
+    pub fn jumps(&self) -> &[(usize, usize)] {
+        &self.jumps
+    }
+
+    /// Index of the jump the cursor is at, if it went back to any.
+    pub fn current(&self) -> Option<usize> {
+        Some(self.current).filter(|c| *c < self.jumps.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{JumpList, MAX_JUMPS};
+
+    #[test]
+    fn test_back_and_forward() {
+        let mut jumps = JumpList::default();
+        assert_eq!(jumps.back((0, 0)), None);
+        assert_eq!(jumps.forward(), None);
+
+        let mut jumps = JumpList::default();
+        jumps.push((1, 0));
+        jumps.push((5, 2));
+
+        assert_eq!(jumps.back((9, 4)), Some((5, 2)));
+        assert_eq!(jumps.current(), Some(1));
+        assert_eq!(jumps.back((5, 2)), Some((1, 0)));
+        assert_eq!(jumps.back((1, 0)), None);
+        assert_eq!(jumps.forward(), Some((5, 2)));
+        assert_eq!(jumps.forward(), Some((9, 4)));
+        assert_eq!(jumps.forward(), None);
+
+        assert_eq!(jumps.jumps(), &[(1, 0), (5, 2), (9, 4)]);
+    }
+
+    #[test]
+    fn test_push() {
+        let mut jumps = JumpList::default();
+        jumps.push((1, 0));
+        jumps.push((5, 2));
+        jumps.push((1, 0));
+        assert_eq!(jumps.jumps(), &[(5, 2), (1, 0)]);
+        assert_eq!(jumps.current(), None);
+
+        // jumping after going back forgets the newer jumps
+        jumps.back((7, 0));
+        jumps.back((1, 0));
+        jumps.push((5, 2));
+        assert_eq!(jumps.jumps(), &[(5, 2)]);
+
+        for r in 0..MAX_JUMPS + 10 {
+            jumps.push((r, 0));
+        }
+        assert_eq!(jumps.jumps().len(), MAX_JUMPS);
+        assert_eq!(jumps.jumps()[0], (10, 0));
+    }
+}
@@ -0,0 +1,124 @@
+use termion::event::Key;
+
+/// Counts are capped so that repeating a command many times stays fast.
+const MAX_COUNT: usize = 999_999;
+
+/// A command typed in normal mode made of an optional count followed by a key
+/// or by a prefix key and another key, like `20j`, `gg` or `zc`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Command {
+    pub count: Option<usize>,
+    pub prefix: Option<char>,
+    pub key: Key,
+}
+
+/// Parser of the key sequences that make up the commands, it's fed one key at
+/// a time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyParser {
+    // keys that need another key to complete the command
+    prefixes: &'static [char],
+
+    count: Option<usize>,
+    prefix: Option<char>,
+}
+
+impl KeyParser {
+    /// Create a parser where the given keys, like "z" for the folds, are the
+    /// first of two keys commands.
+    pub fn new(prefixes: &'static [char]) -> Self {
+        KeyParser {
+            prefixes,
+            count: None,
+            prefix: None,
+        }
+    }
+
+    /// Feed the next key returning the command it completes, if any.
+    pub fn push(&mut self, key: Key) -> Option<Command> {
+        if self.prefix.is_none() {
+            match key {
+                // a leading 0 is a command on its own, like moving to the
+                // start of the line
+                Key::Char(c @ '0'..='9') if c != '0' || self.count.is_some() => {
+                    let digit = c.to_digit(10).unwrap() as usize;
+                    self.count = Some((self.count.unwrap_or(0) * 10 + digit).min(MAX_COUNT));
+                    return None;
+                }
+                Key::Char(c) if self.prefixes.contains(&c) => {
+                    self.prefix = Some(c);
+                    return None;
+                }
+                _ => {}
+            }
+        }
+
+        Some(Command {
+            count: self.count.take(),
+            prefix: self.prefix.take(),
+            key,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Command, KeyParser, MAX_COUNT};
+    use termion::event::Key;
+
+    fn parse(parser: &mut KeyParser, keys: &str) -> Vec<Command> {
+        keys.chars()
+            .filter_map(|c| parser.push(Key::Char(c)))
+            .collect()
+    }
+
+    fn command(count: Option<usize>, prefix: Option<char>, key: char) -> Command {
+        Command {
+            count,
+            prefix,
+            key: Key::Char(key),
+        }
+    }
+
+    #[test]
+    fn test_key_parser() {
+        let mut parser = KeyParser::new(&['g', 'z']);
+
+        assert_eq!(parse(&mut parser, "j"), vec![command(None, None, 'j')]);
+        assert_eq!(
+            parse(&mut parser, "20j"),
+            vec![command(Some(20), None, 'j')]
+        );
+        assert_eq!(parse(&mut parser, "0"), vec![command(None, None, '0')]);
+        assert_eq!(
+            parse(&mut parser, "gg10G"),
+            vec![command(None, Some('g'), 'g'), command(Some(10), None, 'G')]
+        );
+        assert_eq!(
+            parse(&mut parser, "3zc"),
+            vec![command(Some(3), Some('z'), 'c')]
+        );
+
+        // digits after a prefix complete the command
+        assert_eq!(
+            parse(&mut parser, "z1"),
+            vec![command(None, Some('z'), '1')]
+        );
+
+        assert_eq!(parse(&mut parser, "12"), vec![]);
+        assert_eq!(
+            parser.push(Key::Esc),
+            Some(Command {
+                count: Some(12),
+                prefix: None,
+                key: Key::Esc
+            })
+        );
+        assert_eq!(parse(&mut parser, "j"), vec![command(None, None, 'j')]);
+
+        assert_eq!(
+            parse(&mut parser, "99999999999999999999999j"),
+            vec![command(Some(MAX_COUNT), None, 'j')]
+        );
+    }
+}
@@ -1,2 +1,8 @@
+pub mod clipboard;
+pub mod convert;
+pub mod events;
+pub mod format;
 pub mod json;
+pub mod jump_list;
+pub mod keys;
 pub mod widgets;
@@ -1,21 +1,39 @@
+use std::borrow::Cow;
 use std::fs;
 use std::io;
 use std::io::Read;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
+use regex::{Regex, RegexBuilder};
 use structopt::StructOpt;
+use unicode_segmentation::UnicodeSegmentation;
 
 use termion::clear;
 use termion::color;
-use termion::event::Key;
-use termion::input::TermRead;
+use termion::event::{Key, MouseButton, MouseEvent};
+use termion::input::MouseTerminal;
 use termion::raw::{IntoRawMode, RawTerminal};
 
-use jv::json::index::{index, Index};
-use jv::json::{parse_json, JsonTokenTag};
-use jv::widgets::ascii_line::AsciiLine;
+use jv::clipboard;
+use jv::convert::{bson, cbor, json5, msgpack, toml, yaml};
+use jv::events::{self, Event};
+use jv::format::{Format, Parsed};
+use jv::json::completion::{closest, common_prefix, complete, Completion};
+use jv::json::container::{containers, matching_bracket, Container};
+use jv::json::index::{index, index_records, keys, Index, ReverseIndex};
+use jv::json::jq::Query;
+use jv::json::jsonpath::JsonPath;
+use jv::json::{
+    add_comments, annotate, parse_json, parse_ndjson, pointer, unquote, Annotation, Comment,
+    JsonLine, JsonToken, JsonTokenTag, KeyOrder,
+};
+use jv::jump_list::JumpList;
+use jv::keys::KeyParser;
+use jv::widgets::finder::{Finder, FinderItem};
 use jv::widgets::status_line::{StatusLine, StatusLineMode};
-use jv::widgets::view::{Line, View};
+use jv::widgets::unicode_line::UnicodeLine;
+use jv::widgets::view::{Line, ScreenPosition, View};
 use jv::widgets::Widget;
 
 const HELP_TEXT: &str = r##"
@@ -32,14 +50,23 @@ also as a basic viewer for plain text files.
 
 # Manual
 
-JV has three "modes": COMMAND, QUERY and HELP. You can enter COMMAND mode with
-":", QUERY with "#" and help with h from COMMAND mode.
+JV has four "modes": COMMAND, QUERY, SEARCH and HELP. You can enter COMMAND
+mode with ":", QUERY with "#", SEARCH with "/" or "?" and help with h from
+COMMAND mode.
 
 To quit JV hit q while focusing the buffer or :q in COMMAND mode.
 
 JV uses vim-like navigation, you can move around with the arrow keys or with J,
 H, K, L. Use 0 and $ to go to the beginning and at the end of the current line
-respectively.
+respectively, w, b and e to go to the next word, previous word and end of the
+word, where in JSON every string, number and bracket is a word. "gg" and "G" go
+to the first and last line, H, M and L to the top, middle and bottom of the
+screen. Ctrl-D and Ctrl-U scroll half a page down and up while Ctrl-E and
+Ctrl-Y scroll a single line. Prefix a motion with a count to repeat it, like
+"20j", or "42G" to go to line 42.
+
+The mouse works too: click to move the cursor, click the placeholder of a fold
+to open it, scroll with the wheel and double click a reference to follow it.
 
 Go to a given line and or column by typing in COMMAND mode the line and column
 number separated by a ":". It's possible to omit either the row or column
@@ -48,24 +75,120 @@ and "1".
 
 Use a jq-like query to quickly jump to an element of a JSON document. First,
 enter query mode with "#" and then enter object keys or array indices separated
-by "/" . Example queries: "#/", "#/array/23/name", "#/23".
+by "/" . Example queries: "#/", "#/array/23/name", "#/23". The reference of the
+value under the cursor is always shown in the status line. Hit Tab to complete
+the keys or indices of the path, the possible completions are listed along with
+the type of their values and hitting Tab again cycles through them.
+
+Hit enter on a reference like "#/definitions/Id" to go to the value it points
+to. References to other files, like "common.json#/definitions/Id", open the file
+relative to the current one and Ctrl-T goes back to the previous file.
+
+Hit Ctrl-P to fuzzy find any reference of the document, the best matches are
+listed first along with a preview of the selected value. Move through them with
+the arrows, Ctrl-P and Ctrl-N, hit Ctrl-V to match the values too and enter to
+go to the selected one.
+
+Going to a reference, a line, a search match or a query result is a jump. Use
+Ctrl-O to go back to where the cursor was before a jump and Ctrl-I, or Tab, to
+go forward again. The ":jumps" command shows the list of the jumps.
+
+Use "*" in a path to match all the items of an array or all the values of an
+object, like "#/items/*/name", or write a jq filter instead, like
+"#.items[] | select(.status == "failed")". The supported jq subset includes
+paths, ".[]", "..", pipes, comparisons, "and", "or", "not", "select", "map",
+"keys" and "length". Queries starting with "$" are JSONPath expressions
+instead, like "#$..name", "#$.items[?(@.age > 30)]" or "#$.items[1:10:2]". When
+all the results are values of the document they are highlighted and "n" and "N"
+go through them, otherwise they're shown in a separate view, hit q to close it.
+
+A malformed JSON document, like a .json file with a mistake, is shown as plain
+text with the cursor on the error, which is highlighted like a search match, and
+the error in the status line.
+
+JSON documents with comments, trailing commas, unquoted keys, single quoted
+strings and the other extensions of JSON5 are viewed like any other JSON, and
+their comments are shown in gray next to the values they belong to.
+
+Newline delimited JSON files, also known as JSON Lines, are shown one record
+after the other and each record is referenced by its index, so "#/3/name" is
+the name in the fourth record. The lines that are not valid JSON are shown in
+red followed by the error, and queries see them as strings of their raw text.
+
+YAML files are shown as JSON and navigated and queried the same way. Files with
+many documents are shown like JSON Lines, so "#/1/kind" is the kind of the
+second document. The aliases and the "<<" merge keys are resolved, and the
+aliased values are preceded by the reference of their anchor in gray, which can
+be followed with enter. TOML files are shown as JSON too, the arrays of tables
+are arrays like "#/bin/0/name" and the datetimes are highlighted in blue.
+
+MessagePack, CBOR and BSON files are shown as JSON as well. The binary data is
+shown as a preview of its first bytes in hex, though its value is base64, and
+the types JSON doesn't have are shown in gray before their value, like
+"ObjectId" or "tag(1)".
+
+Search forward with "/" and backward with "?" followed by a regex. The matches
+are highlighted while typing and the search is case insensitive unless the
+pattern contains an uppercase letter. Use "n" and "N" to go to the next and
+previous match respectively and ESC to clear the highlighting.
+
+Copy to the clipboard the current line with "yy", the reference of the value
+under the cursor with "yp", the value itself with "yv" and the object or array
+around the cursor with "yc" as compact JSON or with "yC" pretty printed. The
+text is sent to the terminal via OSC 52 so it works over SSH too as long as the
+terminal supports it.
+
+Fold the object or array under the cursor with "zc", open it again with "zo"
+and toggle it with "za". "zM" folds everything while "zR" opens all the folds.
+Fold all the containers deeper than a given level by typing "fold" followed by
+the depth in COMMAND mode, for example "fold 2", and open them all with
+"unfold".
+
+Move through the structure of the document with "%" to jump between matching
+brackets, "[" to go to the parent container, "{" and "}" to go to its first and
+last child and "(" and ")" to go to the previous and next sibling. Hit "K" to
+toggle moving between the object keys, instead of the lines, with j and k.
+
+The keys of the objects are sorted alphabetically, hit "S" to toggle between
+that and the order they appear in the document. Start JV with --preserve-order
+to show them in document order from the beginning.
 
 To exit this help page hit q.
 "##;
 
+/// Maximum time between two clicks for them to be a double click.
+const DOUBLE_CLICK_INTERVAL: Duration = Duration::from_millis(400);
+
+/// Number of rows the mouse wheel scrolls at a time.
+const WHEEL_ROWS: usize = 3;
+
 /// Simple json viewer that allows querying and jumping to json values via
 /// jq-like queries with format "#/<objectkey>/<arrayix>". An example query is
 /// `#/authors/1` or `#/dependencies/react`.
 ///
 /// You can write a query by entering query mode with `#` and writing the
 /// desired query. Moreover, if the cursor is under a valid query text then you
-/// can automatically jump to it with `ENTER`. If the input filename doesn't end
-/// with ".json" then it's not treated as such and `jv` will simply work as a
-/// viewer.
+/// can automatically jump to it with `ENTER`. The format of the input is
+/// detected from its content, if it doesn't look like a JSON document then
+/// it's not treated as such and `jv` will simply work as a viewer.
 #[derive(Debug, StructOpt)]
 struct Opts {
+    /// File to view, if it's missing or it's "-" the input is read from stdin.
     #[structopt(parse(from_os_str))]
-    input: PathBuf,
+    input: Option<PathBuf>,
+
+    /// Format of the input, overrides the one detected from the content.
+    #[structopt(
+        short = "f",
+        long = "format",
+        raw(possible_values = "Format::variants()", case_insensitive = "true")
+    )]
+    format: Option<Format>,
+
+    /// Show the keys of the objects in the order they appear in the input
+    /// instead of sorting them alphabetically.
+    #[structopt(long = "preserve-order")]
+    preserve_order: bool,
 }
 
 struct Ui<L, W, Q>
@@ -77,17 +200,97 @@ where
 
     view: View<L>,
     help_view: View<HelpLine>,
+    results_view: View<JsonLine>,
     status_line: StatusLine,
+    finder: Finder,
 
     focus: Focus,
 
-    index: Index,
+    // parser of the commands with counts and multiple keys, like "20j" or "zc"
+    keys: KeyParser,
+
+    // whether j and k move between the object keys instead of the lines
+    keys_mode: bool,
+
+    // position of the cursor when the search started and its direction
+    search_origin: (usize, usize),
+    search_forward: bool,
+
+    // positions the cursor jumped away from, Ctrl-O and Ctrl-I go through them
+    jumps: JumpList,
+
+    // time and position of the last click to detect double clicks
+    last_click: Option<(Instant, (u16, u16))>,
+
+    // completions of the query being cycled through with Tab
+    completions: Option<Completions>,
+
+    // file being viewed, it's None for stdin
+    path: Option<PathBuf>,
+    input: Vec<u8>,
+    doc: Document,
+    key_order: KeyOrder,
+
+    // documents to go back to after following references to other files
+    back: Vec<Buffer<L>>,
+
+    // parse the input into lines, it's called again when the order of the
+    // keys changes
+    load: Loader<L>,
+
+    // the files that references point to are opened with it, according to
+    // their own format
+    open: Opener<L>,
+
     get_current_query: Q,
 }
 
+type Loader<L> = fn(&[u8], KeyOrder) -> Result<(Vec<L>, Document)>;
+
+/// Detect the format of an input and load it, along with the loader of its
+/// format.
+type Opener<L> = fn(&[u8], KeyOrder) -> Result<Opened<L>>;
+
+type Opened<L> = (Loader<L>, (Vec<L>, Document));
+
+/// The completions of the last segment of a query path.
+struct Completions {
+    // query up to the segment being completed
+    base: String,
+    candidates: Vec<Completion>,
+    current: usize,
+}
+
+/// A document that was being viewed before following a reference to another
+/// file.
+struct Buffer<L> {
+    path: Option<PathBuf>,
+    input: Vec<u8>,
+    load: Loader<L>,
+    doc: Document,
+    key_order: KeyOrder,
+    view: View<L>,
+    jumps: JumpList,
+}
+
+/// The structure of the document being viewed, it's empty for plain text.
+#[derive(Debug, Default)]
+struct Document {
+    value: Option<serde_json::Value>,
+    index: Index,
+    reverse_index: ReverseIndex,
+    containers: Vec<Container>,
+
+    // positions of the object keys
+    keys: Vec<(usize, usize)>,
+
+    // number of records for NDJSON
+    records: Option<usize>,
+}
+
 #[derive(Debug)]
 struct HelpLine {
-    line: AsciiLine<&'static str>,
+    line: UnicodeLine<&'static str>,
     logo: bool,
 }
 
@@ -116,6 +319,10 @@ impl Line for HelpLine {
     fn char_width(&self, idx: usize) -> u16 {
         self.line.char_width(idx)
     }
+
+    fn text(&self) -> Cow<'_, str> {
+        self.line.text()
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -123,6 +330,8 @@ enum Focus {
     View,
     StatusLine,
     Help,
+    Results,
+    Finder,
 }
 
 type Result<T> = std::result::Result<T, Error>;
@@ -130,50 +339,73 @@ type Result<T> = std::result::Result<T, Error>;
 #[derive(Debug)]
 enum Error {
     Io(io::Error),
-    NotUnicode(String),
     Json(serde_json::Error),
+    Json5(json5::Error),
+    Yaml(yaml_rust2::ScanError),
+    Toml(toml::Error),
+    MessagePack(msgpack::Error),
+    Cbor(cbor::Error),
+    Bson(bson::Error),
+    NoInput,
 }
 
 fn main() {
     fn _main() -> Result<()> {
         let opts = Opts::from_args();
 
-        let mut f = fs::File::open(&opts.input)?;
-
-        match opts.input.extension() {
-            Some(e) if e == "json" => {
-                let lines = parse_json(serde_json::from_reader(f)?).map_err(Error::NotUnicode)?;
-                let index = index(&lines);
-                // dbg!(&index);
-
-                let mut ui = Ui::new(lines, index, |v| {
-                    if let Some(jt) = v.current_line().and_then(|r| r.token_at(v.col())) {
-                        if jt.tag() == JsonTokenTag::Ref {
-                            let mut q = jt.text().to_string();
+        let input = read_input(opts.input.as_deref())?;
+        let path = opts.input.filter(|p| p != Path::new("-"));
 
-                            // remove ""
-                            q.pop();
-                            q.remove(0);
-
-                            return Some(q);
-                        }
-                    }
+        let key_order = if opts.preserve_order {
+            KeyOrder::Document
+        } else {
+            KeyOrder::Sorted
+        };
+
+        let (format, parsed) = match opts.format {
+            Some(format) => (format, None),
+            None => match Format::detect_and_parse(&input) {
+                // a malformed document doesn't look like JSON, but the
+                // extension of the file still tells what it's meant to be
+                (Format::Text, _) if path.as_deref().is_some_and(has_json_extension) => {
+                    (Format::Json, None)
+                }
+                detected => detected,
+            },
+        };
 
-                    None
-                })?;
+        if format == Format::Text {
+            return Ui::new(path, input, key_order, load_text, text_open, |_| None)?.run();
+        }
 
-                ui.run()?;
+        // the input parsed to detect its format isn't parsed again
+        let load = json_loader(format);
+        let loaded = match parsed {
+            Some(parsed) => Ok(load_parsed(parsed, key_order)),
+            None => load(&input, key_order),
+        };
+
+        match loaded {
+            Ok(loaded) => {
+                Ui::with_lines(path, input, key_order, load, json_open, loaded, json_query)?
+                    .run()?
             }
-            _ => {
-                let mut input = String::new();
-                f.read_to_string(&mut input)?;
 
-                let lines = input
-                    .lines()
-                    .map(|l| AsciiLine::new(l).map_err(|e| Error::NotUnicode(e.to_string())))
-                    .collect::<Result<Vec<_>>>();
-
-                let mut ui = Ui::new(lines?, Index::new(), |_| None)?;
+            // a malformed document is when a viewer is needed the most, so it's
+            // shown as text with the error located
+            Err(e) => {
+                let (line, column) = match &e {
+                    Error::Json(e) => (e.line(), e.column()),
+                    Error::Json5(e) => (e.line, e.column),
+                    _ => return Err(e),
+                };
+
+                let loaded = load_text(&input, key_order)?;
+                let mut ui =
+                    Ui::with_lines(path, input, key_order, load_text, text_open, loaded, |_| {
+                        None
+                    })?;
+                ui.show_error(line, column, &e.to_string());
                 ui.run()?;
             }
         }
@@ -187,13 +419,77 @@ fn main() {
     }
 }
 
-impl<L, Q> Ui<L, io::Stdout, Q>
+impl Document {
+    fn new(value: serde_json::Value, lines: &[JsonLine]) -> Self {
+        let (index, reverse_index) = index(lines);
+
+        Document {
+            value: Some(value),
+            index,
+            reverse_index,
+            containers: containers(lines),
+            keys: keys(lines),
+            records: None,
+        }
+    }
+
+    /// Create the document of the records of a NDJSON file.
+    fn records(records: Vec<serde_json::Value>, lines: &[JsonLine]) -> Self {
+        let (index, reverse_index) = index_records(lines);
+
+        Document {
+            records: Some(records.len()),
+            value: Some(serde_json::Value::Array(records)),
+            index,
+            reverse_index,
+            containers: containers(lines),
+            keys: keys(lines),
+        }
+    }
+
+    /// Get the value at the given reference.
+    fn get(&self, path: &str) -> Option<&serde_json::Value> {
+        self.value.as_ref()?.pointer(path.trim_start_matches('#'))
+    }
+}
+
+impl<L, Q> Ui<L, MouseTerminal<io::Stdout>, Q>
 where
     L: Line,
     Q: Fn(&View<L>) -> Option<String>,
 {
-    fn new(lines: Vec<L>, index: Index, get_current_query: Q) -> Result<Self> {
-        let stdout = io::stdout().into_raw_mode()?;
+    fn new(
+        path: Option<PathBuf>,
+        input: Vec<u8>,
+        key_order: KeyOrder,
+        load: Loader<L>,
+        open: Opener<L>,
+        get_current_query: Q,
+    ) -> Result<Self> {
+        let loaded = load(&input, key_order)?;
+
+        Self::with_lines(
+            path,
+            input,
+            key_order,
+            load,
+            open,
+            loaded,
+            get_current_query,
+        )
+    }
+
+    /// Create the ui of the given input that's already been loaded.
+    fn with_lines(
+        path: Option<PathBuf>,
+        input: Vec<u8>,
+        key_order: KeyOrder,
+        load: Loader<L>,
+        open: Opener<L>,
+        (lines, doc): (Vec<L>, Document),
+        get_current_query: Q,
+    ) -> Result<Self> {
+        let stdout = MouseTerminal::from(io::stdout()).into_raw_mode()?;
         let (width, height) = termion::terminal_size()?;
 
         let help_view = View::new(
@@ -203,19 +499,34 @@ where
                 .skip(1)
                 .enumerate()
                 .map(|(i, l)| HelpLine {
-                    line: AsciiLine::new(l).unwrap(),
+                    line: UnicodeLine::new(l),
                     logo: i < 8,
                 }),
         );
 
         Ok(Ui {
             focus: Focus::View,
-            status_line: StatusLine::new(height - 2, width),
-            view: View::new((width, height - 2), lines),
+            status_line: StatusLine::new(height.saturating_sub(2), width),
+            view: View::new((width, height.saturating_sub(2)), lines),
+            keys: KeyParser::new(&['g', 'z', 'y']),
+            keys_mode: false,
+            search_origin: (0, 0),
+            search_forward: true,
+            jumps: JumpList::default(),
+            last_click: None,
+            completions: None,
             get_current_query,
-            index,
+            path,
+            input,
+            doc,
+            key_order,
+            back: vec![],
+            load,
+            open,
             stdout,
             help_view,
+            results_view: View::new((width, height), vec![]),
+            finder: Finder::new((width, height), vec![]),
         })
     }
 }
@@ -239,35 +550,55 @@ where
     fn run(&mut self) -> Result<()> {
         self.clear()?;
 
+        self.update_path();
         self.status_line.render(&mut self.stdout)?;
         self.view.render(&mut self.stdout)?;
         self.view.focus(&mut self.stdout)?;
 
-        for ev in io::stdin().keys() {
-            let quit = match self.focus {
-                Focus::View => self.update_view(ev?)?,
-                Focus::StatusLine => self.update_status_line(ev?)?,
-                Focus::Help => self.update_help_view(ev?)?,
+        for ev in events::events()? {
+            let quit = match ev? {
+                Event::Resize => {
+                    self.resize()?;
+                    false
+                }
+                Event::Key(key) => match self.focus {
+                    Focus::View => self.update_view(key)?,
+                    Focus::StatusLine => self.update_status_line(key)?,
+                    Focus::Help => self.update_help_view(key)?,
+                    Focus::Results => self.update_results_view(key)?,
+                    Focus::Finder => self.update_finder(key)?,
+                },
+                Event::Mouse(mouse) => self.update_mouse(mouse)?,
             };
 
             if quit {
                 break;
             }
 
-            if self.focus == Focus::Help {
-                self.help_view.render(&mut self.stdout)?;
-            } else {
-                self.status_line.render(&mut self.stdout)?;
-                self.view.render(&mut self.stdout)?;
+            let (current_match, matches) = self.view.search_position();
+            self.status_line
+                .set_matches(Some((current_match, matches)).filter(|_| matches > 0));
+            self.update_path();
+
+            match self.focus {
+                Focus::Help => self.help_view.render(&mut self.stdout)?,
+                Focus::Results => self.results_view.render(&mut self.stdout)?,
+                Focus::Finder => self.finder.render(&mut self.stdout)?,
+                Focus::View | Focus::StatusLine => {
+                    self.status_line.render(&mut self.stdout)?;
+                    self.view.render(&mut self.stdout)?;
+                }
             }
 
             match self.focus {
                 Focus::View => self.view.focus(&mut self.stdout)?,
                 Focus::StatusLine => self.status_line.focus(&mut self.stdout)?,
                 Focus::Help => self.help_view.focus(&mut self.stdout)?,
+                Focus::Results => self.results_view.focus(&mut self.stdout)?,
+                Focus::Finder => self.finder.focus(&mut self.stdout)?,
             }
 
-            self.status_line.no_error();
+            self.status_line.no_messages();
         }
 
         self.clear()?;
@@ -275,17 +606,132 @@ where
         Ok(())
     }
 
+    /// Lay out the widgets again according to the new size of the terminal.
+    fn resize(&mut self) -> Result<()> {
+        let (width, height) = termion::terminal_size()?;
+
+        self.view.resize((width, height.saturating_sub(2)));
+        self.help_view.resize((width, height));
+        self.results_view.resize((width, height));
+        self.finder.resize((width, height));
+        self.status_line.resize(height.saturating_sub(2), width);
+
+        self.clear()?;
+
+        Ok(())
+    }
+
+    /// Show the reference of the value under the cursor in the status line.
+    fn update_path(&mut self) {
+        let path = self
+            .doc
+            .reverse_index
+            .get(self.view.current_row(), self.view.col());
+
+        // the records are referenced as "#/<record>/..."
+        let record = self.doc.records.map(|total| {
+            let current = path
+                .and_then(|p| p.split('/').nth(1))
+                .and_then(|r| r.parse().ok());
+
+            (current, total)
+        });
+
+        self.status_line.set_path(path.map(str::to_string));
+        self.status_line.set_record(record);
+    }
+
     fn update_view(&mut self, ev: Key) -> Result<bool> {
+        let cmd = match self.keys.push(ev) {
+            Some(cmd) => cmd,
+            None => return Ok(false),
+        };
+
+        match (cmd.prefix, cmd.key) {
+            (Some('z'), k) => self.update_folds(k),
+            (Some('y'), k) => self.yank(k)?,
+            (Some('g'), Key::Char('g')) => self.goto_line(cmd.count.unwrap_or(1)),
+            (Some(_), _) => {}
+            (None, Key::Char('G')) => self.goto_line(cmd.count.unwrap_or(usize::MAX)),
+            (None, Key::Char(c @ 'n')) | (None, Key::Char(c @ 'N')) => {
+                let from = self.cursor();
+                for _ in 0..cmd.count.unwrap_or(1) {
+                    self.view.next_match(self.search_forward == (c == 'n'));
+                }
+                self.record_jump(from);
+            }
+            (None, k) if self.move_cursor(k) => {
+                for _ in 1..cmd.count.unwrap_or(1) {
+                    self.move_cursor(k);
+                }
+            }
+            (None, k) => return self.run_view_command(k),
+        }
+
+        Ok(false)
+    }
+
+    /// Move the cursor according to the given key returning whether the key is
+    /// a motion, motions are repeated when preceded by a count.
+    fn move_cursor(&mut self, ev: Key) -> bool {
         match ev {
-            Key::Char('q') => return Ok(true),
             Key::Right | Key::Char('l') => self.view.move_right(),
             Key::Left | Key::Char('h') => self.view.move_left(),
+            Key::Up | Key::Char('k') if self.keys_mode => self.move_to_key(false),
+            Key::Down | Key::Char('j') if self.keys_mode => self.move_to_key(true),
             Key::Up | Key::Char('k') => self.view.move_up(),
             Key::Down | Key::Char('j') => self.view.move_down(),
-            Key::Char('0') => self.view.move_to_sol(),
-            Key::Char('$') => self.view.move_to_eol(),
+            Key::Char('w') => self.view.move_to_next_word(),
+            Key::Char('b') => self.view.move_to_prev_word(),
+            Key::Char('e') => self.view.move_to_word_end(),
+            Key::Char('[') => self.structural_motion(ReverseIndex::parent),
+            Key::Char('{') => self.structural_motion(ReverseIndex::first_child),
+            Key::Char('}') => self.structural_motion(ReverseIndex::last_child),
+            Key::Char('(') => self.structural_motion(ReverseIndex::prev_sibling),
+            Key::Char(')') => self.structural_motion(ReverseIndex::next_sibling),
             Key::PageUp => self.view.page_up(),
             Key::PageDown => self.view.page_down(),
+            Key::Ctrl('d') => self.view.half_page_down(),
+            Key::Ctrl('u') => self.view.half_page_up(),
+            Key::Ctrl('e') => self.view.scroll_down(),
+            Key::Ctrl('y') => self.view.scroll_up(),
+            _ => return false,
+        }
+
+        true
+    }
+
+    fn run_view_command(&mut self, ev: Key) -> Result<bool> {
+        match ev {
+            Key::Char('q') => return Ok(true),
+            Key::Char('%') => {
+                let from = self.cursor();
+                if let Some((r, c)) = matching_bracket(&self.doc.containers, from) {
+                    self.view.goto(r, c);
+                    self.record_jump(from);
+                }
+            }
+            Key::Char('K') => self.toggle_keys_mode(),
+            Key::Char('0') => self.view.move_to_sol(),
+            Key::Char('$') => self.view.move_to_eol(),
+            Key::Char('H') => self.view.move_to_screen_top(),
+            Key::Char('M') => self.view.move_to_screen_middle(),
+            Key::Char('L') => self.view.move_to_screen_bottom(),
+            Key::Char('S') => self.toggle_key_order()?,
+            Key::Char('/') => self.start_search(StatusLineMode::Search),
+            Key::Char('?') => self.start_search(StatusLineMode::ReverseSearch),
+            Key::Ctrl('o') => {
+                if let Some((r, c)) = self.jumps.back(self.cursor()) {
+                    self.view.goto(r, c);
+                }
+            }
+            // Ctrl-I and Tab are the same key in terminals
+            Key::Char('\t') => {
+                if let Some((r, c)) = self.jumps.forward() {
+                    self.view.goto(r, c);
+                }
+            }
+            Key::Esc => self.view.clear_search(),
             Key::Char(':') => {
                 self.focus = Focus::StatusLine;
                 self.status_line.activate(StatusLineMode::Command);
@@ -296,16 +742,81 @@ where
             }
             Key::Char('\n') => {
                 if let Some(q) = (self.get_current_query)(&mut self.view) {
-                    self.goto_ref(&q)?;
+                    self.follow_ref(&q)?;
                 }
             }
+            Key::Ctrl('t') => self.go_back()?,
+            Key::Ctrl('p') => self.open_finder()?,
             _ => {}
         }
 
         Ok(false)
     }
 
+    /// Go to the start of the given line, counting from 1, as a jump.
+    fn goto_line(&mut self, line: usize) {
+        let from = self.cursor();
+        self.view.goto(line.saturating_sub(1), 0);
+        self.record_jump(from);
+    }
+
+    /// Move the cursor where the left button is clicked, or open the fold whose
+    /// placeholder is clicked, and scroll with the wheel. Double clicking a
+    /// reference follows it.
+    fn update_mouse(&mut self, ev: MouseEvent) -> Result<bool> {
+        // the coordinates are 1-based
+        let (button, pos) = match ev {
+            MouseEvent::Press(button, x, y) => (button, (x.saturating_sub(1), y.saturating_sub(1))),
+            MouseEvent::Release(..) | MouseEvent::Hold(..) => return Ok(false),
+        };
+
+        match self.focus {
+            Focus::View => {
+                let double_click = button == MouseButton::Left
+                    && self
+                        .last_click
+                        .take()
+                        .is_some_and(|(t, p)| p == pos && t.elapsed() < DOUBLE_CLICK_INTERVAL);
+
+                if button == MouseButton::Left && !double_click {
+                    self.last_click = Some((Instant::now(), pos));
+                }
+
+                mouse(&mut self.view, button, pos);
+
+                if double_click {
+                    if let Some(q) = (self.get_current_query)(&self.view) {
+                        self.follow_ref(&q)?;
+                    }
+                }
+            }
+            Focus::Help => mouse(&mut self.help_view, button, pos),
+            Focus::Results => mouse(&mut self.results_view, button, pos),
+            Focus::Finder => {
+                match button {
+                    MouseButton::WheelUp => self.finder.up(),
+                    MouseButton::WheelDown => self.finder.down(),
+                    _ => return Ok(false),
+                }
+
+                self.update_finder_preview();
+            }
+            Focus::StatusLine => {}
+        }
+
+        Ok(false)
+    }
+
     fn update_status_line(&mut self, ev: Key) -> Result<bool> {
+        let searching = match self.status_line.mode() {
+            StatusLineMode::Search | StatusLineMode::ReverseSearch => true,
+            StatusLineMode::Command | StatusLineMode::Query | StatusLineMode::Find => false,
+        };
+
+        if ev != Key::Char('\t') && self.completions.take().is_some() {
+            self.status_line.set_hint(None);
+        }
+
         match ev {
             Key::Esc => {
                 self.status_line.clear();
@@ -329,18 +840,36 @@ where
                         return Ok(false);
                     }
 
-                    match parse_goto(&self.status_line.text()) {
-                        None => self.status_line.set_error(
-                            AsciiLine::new(format!(
-                                "invalid goto line and column ref: {} ",
-                                self.status_line.text()
-                            ))
-                            .map_err(Error::NotUnicode)?,
-                        ),
+                    if self.status_line.text() == "jumps" {
+                        self.status_line.save_history();
+                        self.status_line.clear();
+                        self.show_jumps()?;
+                        return Ok(false);
+                    }
+
+                    if let Some(depth) = parse_fold(self.status_line.text()) {
+                        match depth {
+                            Some(depth) => self.fold_to_depth(depth),
+                            None => self.view.unfold_all(),
+                        }
+
+                        self.status_line.save_history();
+                        self.status_line.clear();
+                        self.focus = Focus::View;
+                        return Ok(false);
+                    }
+
+                    match parse_goto(self.status_line.text()) {
+                        None => self.status_line.set_error(UnicodeLine::new(format!(
+                            "invalid goto line and column ref: {} ",
+                            self.status_line.text()
+                        ))),
 
                         Some((r, c)) => {
+                            let from = self.cursor();
                             self.view
                                 .goto(r.unwrap_or_else(|| self.view.current_row()), c.unwrap_or(0));
+                            self.record_jump(from);
 
                             self.status_line.save_history();
                             self.status_line.clear();
@@ -349,10 +878,48 @@ where
                     }
                 }
                 StatusLineMode::Query => {
-                    let q = format!("#{}", self.status_line.text());
-                    self.goto_ref(&q)?;
+                    let q = self.status_line.text().to_string();
+
+                    if q.is_empty() || (q.starts_with('/') && !q.contains('*')) {
+                        self.goto_ref(&format!("#{}", q))?;
+                    } else {
+                        self.run_query(&q)?;
+                    }
                 }
+                StatusLineMode::Search | StatusLineMode::ReverseSearch => {
+                    match search_regex(self.status_line.text()) {
+                        Err(_) => self.status_line.set_error(UnicodeLine::new(format!(
+                            "invalid search pattern: {}",
+                            self.status_line.text()
+                        ))),
+                        Ok(_) => {
+                            let pattern = self.status_line.text().to_string();
+                            let (_, matches) = self.view.search_position();
+                            self.search_forward = self.status_line.mode() == StatusLineMode::Search;
+
+                            self.status_line.save_history();
+                            self.status_line.clear();
+                            self.record_jump(self.search_origin);
+
+                            if matches == 0 {
+                                self.status_line.set_error(UnicodeLine::new(format!(
+                                    "pattern not found: {}",
+                                    pattern
+                                )));
+                            }
+
+                            self.focus = Focus::View;
+                        }
+                    }
+
+                    return Ok(false);
+                }
+                // the finder has its own input
+                StatusLineMode::Find => {}
             },
+            Key::Char('\t') if self.status_line.mode() == StatusLineMode::Query => {
+                self.complete_query()
+            }
             Key::Char(c) => self.status_line.insert(c),
             Key::Backspace => {
                 self.status_line.remove();
@@ -366,46 +933,1053 @@ where
             _ => {}
         }
 
+        if searching && self.focus == Focus::StatusLine {
+            self.incremental_search();
+        } else if searching && self.status_line.is_empty() {
+            // the search has been cancelled
+            self.view.clear_search();
+            self.view.goto(self.search_origin.0, self.search_origin.1);
+        }
+
         Ok(false)
     }
 
+    fn start_search(&mut self, mode: StatusLineMode) {
+        self.search_origin = (self.view.current_row(), self.view.col());
+
+        self.focus = Focus::StatusLine;
+        self.status_line.activate(mode);
+    }
+
+    /// Highlight the matches of the search being typed and move the cursor to
+    /// the first one starting from where the search started.
+    fn incremental_search(&mut self) {
+        self.view.goto(self.search_origin.0, self.search_origin.1);
+
+        match search_regex(self.status_line.text()) {
+            Ok(re) => {
+                self.view.search(&re);
+                self.view
+                    .next_match(self.status_line.mode() == StatusLineMode::Search);
+            }
+            Err(_) => self.view.clear_search(),
+        }
+    }
+
     fn update_help_view(&mut self, ev: Key) -> Result<bool> {
         match ev {
             Key::Char('q') | Key::Esc => {
                 self.focus = Focus::View;
             }
-            Key::Right | Key::Char('l') => self.help_view.move_right(),
-            Key::Left | Key::Char('h') => self.help_view.move_left(),
-            Key::Up | Key::Char('k') => self.help_view.move_up(),
-            Key::Down | Key::Char('j') => self.help_view.move_down(),
-            Key::Char('0') => self.help_view.move_to_sol(),
-            Key::Char('$') => self.help_view.move_to_eol(),
-            Key::PageUp => self.help_view.page_up(),
-            Key::PageDown => self.help_view.page_down(),
+            ev => navigate(&mut self.help_view, ev),
+        }
+
+        Ok(false)
+    }
+
+    fn update_results_view(&mut self, ev: Key) -> Result<bool> {
+        match ev {
+            Key::Char('q') | Key::Esc => {
+                self.focus = Focus::View;
+            }
+            ev => navigate(&mut self.results_view, ev),
+        }
+
+        Ok(false)
+    }
+
+    /// Move the cursor to the position the given motion finds from the current
+    /// one in the structure of the document.
+    fn structural_motion(
+        &mut self,
+        motion: fn(&ReverseIndex, usize, usize) -> Option<(usize, usize)>,
+    ) {
+        if let Some((r, c)) = motion(
+            &self.doc.reverse_index,
+            self.view.current_row(),
+            self.view.col(),
+        ) {
+            self.view.goto(r, c);
+        }
+    }
+
+    fn toggle_keys_mode(&mut self) {
+        if self.doc.value.is_none() {
+            return;
+        }
+
+        self.keys_mode = !self.keys_mode;
+
+        let info = if self.keys_mode {
+            "j and k move between the keys"
+        } else {
+            "j and k move between the lines"
+        };
+        self.status_line
+            .set_info(UnicodeLine::new(info.to_string()));
+    }
+
+    /// Move the cursor to the next, or previous, object key that's not hidden
+    /// inside a fold.
+    fn move_to_key(&mut self, forward: bool) {
+        let cursor = self.cursor();
+        let visible = |k: &&(usize, usize)| !self.view.is_hidden(k.0);
+
+        let key = if forward {
+            let i = self.doc.keys.partition_point(|k| *k <= cursor);
+            self.doc.keys[i..].iter().find(visible)
+        } else {
+            let i = self.doc.keys.partition_point(|k| *k < cursor);
+            self.doc.keys[..i].iter().rev().find(visible)
+        };
+
+        if let Some((r, c)) = key.cloned() {
+            self.view.goto(r, c);
+        }
+    }
+
+    /// Show the finder over all the references of the document.
+    fn open_finder(&mut self) -> Result<()> {
+        if self.doc.value.is_none() {
+            self.status_line
+                .set_error(UnicodeLine::new("there's no JSON to find in".to_string()));
+            return Ok(());
+        }
+
+        let mut refs = self.doc.index.iter().collect::<Vec<_>>();
+        refs.sort_by_key(|(_, pos)| **pos);
+
+        let items = refs
+            .into_iter()
+            .map(|(path, _)| FinderItem {
+                path: path.clone(),
+                value: self.doc.get(path).and_then(|v| match v {
+                    serde_json::Value::Object(_) | serde_json::Value::Array(_) => None,
+                    v => Some(v.to_string()),
+                }),
+            })
+            .collect();
+
+        self.finder = Finder::new(termion::terminal_size()?, items);
+        self.update_finder_preview();
+        self.focus = Focus::Finder;
+
+        Ok(())
+    }
+
+    fn update_finder(&mut self, ev: Key) -> Result<bool> {
+        match ev {
+            Key::Esc => self.focus = Focus::View,
+            Key::Char('\n') => {
+                self.focus = Focus::View;
+
+                // the paths of the index aren't percent encoded
+                if let Some(path) = self.finder.selected().map(|i| i.path.clone()) {
+                    self.goto_reference(&path)?;
+                }
+                return Ok(false);
+            }
+            Key::Up | Key::Ctrl('p') => self.finder.up(),
+            Key::Down | Key::Ctrl('n') => self.finder.down(),
+            Key::Ctrl('v') => self.finder.toggle_values(),
+            Key::Left => self.finder.left(),
+            Key::Right => self.finder.right(),
+            Key::Backspace => self.finder.remove(),
+            Key::Char(c) => self.finder.insert(c),
             _ => {}
         }
 
+        self.update_finder_preview();
+
         Ok(false)
     }
 
+    /// Show the value of the item selected in the finder as compact JSON.
+    fn update_finder_preview(&mut self) {
+        let preview = self
+            .finder
+            .selected()
+            .and_then(|i| self.doc.get(&i.path))
+            .map(|v| v.to_string());
+
+        self.finder.set_preview(preview);
+    }
+
+    /// Complete the last segment of the path being typed in query mode with the
+    /// keys or indices of the values in the document. Hitting Tab again cycles
+    /// through the completions when there's more than one.
+    fn complete_query(&mut self) {
+        let value = match &self.doc.value {
+            Some(value) => value,
+            None => return,
+        };
+
+        if let Some(completions) = &mut self.completions {
+            completions.current = (completions.current + 1) % completions.candidates.len();
+
+            let c = &completions.candidates[completions.current];
+            self.status_line
+                .set_text(&format!("{}{}", completions.base, c.segment));
+            self.status_line.set_hint(Some(completions_hint(
+                &completions.candidates,
+                Some(completions.current),
+            )));
+            return;
+        }
+
+        let path = match self.status_line.text() {
+            "" => "/".to_string(),
+            text if text.starts_with('/') && !text.contains('*') => text.to_string(),
+            _ => return,
+        };
+
+        let candidates = complete(value, &path, self.key_order);
+        let base = path[..=path.rfind('/').unwrap_or(0)].to_string();
+        let partial = &path[base.len()..];
+
+        match candidates.as_slice() {
+            [] => self
+                .status_line
+                .set_hint(Some("no completions".to_string())),
+            [c] => {
+                let mut text = format!("{}{}", base, c.segment);
+                if c.has_children {
+                    text.push('/');
+                }
+
+                self.status_line.set_text(&text);
+                self.status_line
+                    .set_hint(Some(completions_hint(&candidates, None)));
+            }
+            _ => {
+                let prefix = common_prefix(&candidates);
+                let hint = completions_hint(&candidates, None);
+
+                if prefix.len() > partial.len() {
+                    self.status_line.set_text(&format!("{}{}", base, prefix));
+                    self.status_line.set_hint(Some(hint));
+                } else {
+                    self.status_line
+                        .set_text(&format!("{}{}", base, candidates[0].segment));
+                    self.status_line
+                        .set_hint(Some(completions_hint(&candidates, Some(0))));
+                    self.completions = Some(Completions {
+                        base,
+                        candidates,
+                        current: 0,
+                    });
+                }
+            }
+        }
+    }
+
+    /// Run the given jq query, JSONPath starting with "$" or "/" separated path
+    /// with wildcards, and highlight its results if they are all values of the
+    /// document or show them in the results view otherwise.
+    fn run_query(&mut self, q: &str) -> Result<()> {
+        let value = match &self.doc.value {
+            Some(value) => value,
+            None => {
+                self.status_line
+                    .set_error(UnicodeLine::new("there's no JSON to query".to_string()));
+                return Ok(());
+            }
+        };
+
+        let outputs = if q.starts_with('$') {
+            q.parse::<JsonPath>().map(|path| path.run(value))
+        } else if q.starts_with('/') {
+            Query::from_path(&pointer::percent_decode(q)).run(value)
+        } else {
+            q.parse::<Query>().and_then(|query| query.run(value))
+        };
+
+        let outputs = match outputs {
+            Ok(outputs) => outputs,
+            Err(e) => {
+                self.status_line
+                    .set_error(UnicodeLine::new(format!("invalid query: {}", e)));
+                return Ok(());
+            }
+        };
+
+        if outputs.is_empty() {
+            self.status_line
+                .set_error(UnicodeLine::new(format!("no results for {}", q)));
+            return Ok(());
+        }
+
+        let positions = outputs
+            .iter()
+            .map(|o| {
+                let (r, c) = self.doc.index.get(&o.reference()?)?;
+                Some((*r, *c, *c + value_width(&o.value)))
+            })
+            .collect::<Option<Vec<_>>>();
+
+        self.status_line.save_history();
+        self.status_line.clear();
+
+        match positions {
+            Some(positions) => {
+                let (r, c, _) = positions[0];
+                let n = positions.len();
+
+                let from = self.cursor();
+                self.view.set_matches(positions);
+                self.view.goto(r, c);
+                self.record_jump(from);
+
+                self.status_line.set_info(UnicodeLine::new(format!(
+                    "{} result{}",
+                    n,
+                    if n == 1 { "" } else { "s" }
+                )));
+                self.focus = Focus::View;
+            }
+            None => {
+                let lines = outputs
+                    .into_iter()
+                    .flat_map(|o| parse_json(o.value.into_owned(), self.key_order))
+                    .collect::<Vec<_>>();
+
+                self.results_view = View::new(termion::terminal_size()?, lines);
+                self.focus = Focus::Results;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn update_folds(&mut self, ev: Key) {
+        let row = self.view.current_row();
+        let from = self.cursor();
+
+        match ev {
+            Key::Char('a') => {
+                let unfolded = self.view.unfold(row);
+                if !unfolded {
+                    self.fold_under_cursor();
+                }
+            }
+            Key::Char('o') => {
+                self.view.unfold(row);
+            }
+            Key::Char('c') => self.fold_under_cursor(),
+            Key::Char('M') => self.fold_to_depth(0),
+            Key::Char('R') => self.view.unfold_all(),
+            _ => {}
+        }
+
+        // folding moves the cursor to the start of the fold
+        self.record_jump(from);
+    }
+
+    /// Fold the innermost container around the cursor that's not folded yet.
+    fn fold_under_cursor(&mut self) {
+        let row = self.view.current_row();
+
+        let container =
+            self.doc.containers.iter().rev().find(|c| {
+                c.is_multiline() && c.contains_row(row) && !self.view.is_folded(c.start.0)
+            });
+
+        if let Some(c) = container {
+            self.view.fold(c.start.0, c.end.0, c.summary());
+        }
+    }
+
+    /// Fold all the containers that are at least `depth` levels deep.
+    fn fold_to_depth(&mut self, depth: usize) {
+        self.view.refold(
+            self.doc
+                .containers
+                .iter()
+                .filter(|c| c.is_multiline() && c.depth >= depth)
+                .map(|c| (c.start.0, c.end.0, c.summary())),
+        );
+    }
+
+    /// Copy to the clipboard the current line with "yy", the reference of the
+    /// value under the cursor with "yp", the value itself with "yv" or the
+    /// object or array around the cursor as compact JSON with "yc" or pretty
+    /// printed with "yC".
+    fn yank(&mut self, ev: Key) -> Result<()> {
+        let path = self
+            .doc
+            .reverse_index
+            .get(self.view.current_row(), self.view.col());
+
+        let text = match (ev, path) {
+            (Key::Char('y'), _) => self.view.current_line().map(|l| l.text().into_owned()),
+            (Key::Char('p'), Some(path)) => Some(path.to_string()),
+            (Key::Char('v'), Some(path)) => self.doc.get(path).map(|v| match v {
+                serde_json::Value::String(s) => s.clone(),
+                v => v.to_string(),
+            }),
+            (Key::Char('c'), Some(path)) | (Key::Char('C'), Some(path)) => {
+                let mut path = path;
+                let mut value = self.doc.get(path);
+
+                while value.is_some_and(|v| !v.is_object() && !v.is_array()) {
+                    path = match path.rfind('/') {
+                        Some(i) => &path[..i],
+                        None => break,
+                    };
+                    value = self.doc.get(path);
+                }
+
+                value.map(|v| {
+                    if ev == Key::Char('C') {
+                        serde_json::to_string_pretty(v).unwrap()
+                    } else {
+                        v.to_string()
+                    }
+                })
+            }
+            _ => None,
+        };
+
+        match text {
+            Some(text) => {
+                clipboard::copy(&mut self.stdout, &text)?;
+
+                self.status_line.set_info(UnicodeLine::new(format!(
+                    "copied {} characters to the clipboard",
+                    text.chars().count()
+                )));
+            }
+            None => self.status_line.set_error(UnicodeLine::new(
+                "nothing to copy under the cursor".to_string(),
+            )),
+        }
+
+        Ok(())
+    }
+
+    /// Switch between sorted keys and document order keeping the cursor on the
+    /// value it was on. The folds are lost because the rows change.
+    fn toggle_key_order(&mut self) -> Result<()> {
+        if self.doc.value.is_none() {
+            return Ok(());
+        }
+
+        let path = self
+            .doc
+            .reverse_index
+            .get(self.view.current_row(), self.view.col())
+            .map(str::to_string);
+
+        self.key_order = match self.key_order {
+            KeyOrder::Sorted => KeyOrder::Document,
+            KeyOrder::Document => KeyOrder::Sorted,
+        };
+
+        let (lines, doc) = (self.load)(&self.input, self.key_order)?;
+        self.doc = doc;
+        self.view.set_lines(lines);
+
+        // the rows of the jumps are not valid anymore
+        self.jumps = JumpList::default();
+
+        if let Some((r, c)) = path.and_then(|p| self.doc.index.get(&p)) {
+            self.view.goto(*r, *c);
+        }
+
+        let info = match self.key_order {
+            KeyOrder::Sorted => "keys sorted alphabetically",
+            KeyOrder::Document => "keys in document order",
+        };
+        self.status_line
+            .set_info(UnicodeLine::new(info.to_string()));
+
+        Ok(())
+    }
+
+    /// Go to the error of an invalid document at the given line and column,
+    /// which start from 1 and where the column is in bytes like for
+    /// `serde_json`, highlighting it and showing the message.
+    fn show_error(&mut self, line: usize, column: usize, message: &str) {
+        // column 0 is right after a newline, i.e. at the end of the previous
+        // line, and the errors at the end of the input might be past the last
+        // line
+        let (r, column) = match (line.saturating_sub(1), column) {
+            (r, 0) if r > 0 => (r - 1, usize::MAX),
+            pos => pos,
+        };
+        self.view.goto(r, 0);
+        let column = if self.view.current_row() < r {
+            usize::MAX
+        } else {
+            column
+        };
+
+        if let Some(text) = self.view.current_line().map(|l| l.text().into_owned()) {
+            let (start, end) = error_region(&text, column);
+
+            self.view.goto(self.view.current_row(), start);
+            self.view
+                .set_matches(vec![(self.view.current_row(), start, end)]);
+        }
+
+        self.status_line
+            .set_error(UnicodeLine::new(message.to_string()));
+    }
+
+    /// Go to the given reference, opening the file it points to if it's not
+    /// the current one.
+    fn follow_ref(&mut self, q: &str) -> Result<()> {
+        let (file, pointer) = q.split_once('#').unwrap_or((q, ""));
+        if file.is_empty() {
+            return self.goto_ref(q);
+        }
+
+        let file = pointer::percent_decode(file);
+        let path = match self.path.as_ref().and_then(|p| p.parent()) {
+            Some(dir) => dir.join(&file),
+            None => PathBuf::from(&file),
+        };
+
+        let is_current = self
+            .path
+            .as_ref()
+            .and_then(|p| p.canonicalize().ok())
+            .is_some_and(|p| path.canonicalize().is_ok_and(|path| path == p));
+        if is_current {
+            return self.goto_ref(&format!("#{}", pointer));
+        }
+
+        let loaded = read_input(Some(&path)).and_then(|input| {
+            let (load, loaded) = (self.open)(&input, self.key_order)?;
+            Ok((loaded, input, load))
+        });
+        let ((lines, doc), input, load) = match loaded {
+            Ok(loaded) => loaded,
+            Err(e) => {
+                self.status_line
+                    .set_error(UnicodeLine::new(format!("cannot open {}: {}", file, e)));
+                return Ok(());
+            }
+        };
+
+        let (width, height) = termion::terminal_size()?;
+        let view = View::new((width, height.saturating_sub(2)), lines);
+
+        self.back.push(Buffer {
+            path: self.path.replace(path),
+            input: std::mem::replace(&mut self.input, input),
+            load: std::mem::replace(&mut self.load, load),
+            doc: std::mem::replace(&mut self.doc, doc),
+            key_order: self.key_order,
+            view: std::mem::replace(&mut self.view, view),
+            jumps: std::mem::take(&mut self.jumps),
+        });
+
+        self.status_line
+            .set_info(UnicodeLine::new(format!("opened {}", file)));
+
+        if !pointer.is_empty() {
+            self.goto_ref(&format!("#{}", pointer))?;
+        }
+
+        Ok(())
+    }
+
+    /// Go back to the document that was being viewed before following a
+    /// reference to another file.
+    fn go_back(&mut self) -> Result<()> {
+        let buffer = match self.back.pop() {
+            Some(buffer) => buffer,
+            None => {
+                self.status_line
+                    .set_error(UnicodeLine::new("no file to go back to".to_string()));
+                return Ok(());
+            }
+        };
+
+        self.path = buffer.path;
+        self.input = buffer.input;
+        self.load = buffer.load;
+        self.doc = buffer.doc;
+        self.key_order = buffer.key_order;
+        self.view = buffer.view;
+        self.jumps = buffer.jumps;
+
+        // the terminal might have been resized in the meantime
+        let (width, height) = termion::terminal_size()?;
+        self.view.resize((width, height.saturating_sub(2)));
+
+        if let Some(name) = self.path.as_ref().and_then(|p| p.file_name()) {
+            self.status_line.set_info(UnicodeLine::new(format!(
+                "back to {}",
+                name.to_string_lossy()
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Position of the cursor in the document.
+    fn cursor(&self) -> (usize, usize) {
+        (self.view.current_row(), self.view.col())
+    }
+
+    /// Remember the given position in the jump list if the cursor moved away
+    /// from it.
+    fn record_jump(&mut self, from: (usize, usize)) {
+        if from != self.cursor() {
+            self.jumps.push(from);
+        }
+    }
+
+    /// Show the jump list in the results view, the jump the cursor is at while
+    /// going through it is marked as current.
+    fn show_jumps(&mut self) -> Result<()> {
+        let jumps = self
+            .jumps
+            .jumps()
+            .iter()
+            .enumerate()
+            .map(|(i, (r, c))| {
+                let mut jump = serde_json::Map::new();
+                jump.insert("line".to_string(), (r + 1).into());
+                jump.insert("col".to_string(), (c + 1).into());
+
+                if let Some(path) = self.doc.reverse_index.get(*r, *c) {
+                    jump.insert("ref".to_string(), path.into());
+                }
+                if self.jumps.current() == Some(i) {
+                    jump.insert("current".to_string(), true.into());
+                }
+
+                serde_json::Value::Object(jump)
+            })
+            .collect();
+
+        let lines = parse_json(serde_json::Value::Array(jumps), KeyOrder::Document);
+        self.results_view = View::new(termion::terminal_size()?, lines);
+        self.focus = Focus::Results;
+
+        Ok(())
+    }
+
     fn goto_ref(&mut self, q: &str) -> Result<()> {
-        match self.index.get(q.trim_end_matches('/')) {
+        self.goto_reference(&pointer::percent_decode(q))
+    }
+
+    /// Jump to the given reference exactly as it's in the index, that is not
+    /// percent encoded.
+    fn goto_reference(&mut self, reference: &str) -> Result<()> {
+        // a trailing "/" is an empty key, but "#/" is the root too if there's
+        // no such key
+        let position = self.doc.index.get(reference).or_else(|| match reference {
+            "#/" => self.doc.index.get("#"),
+            _ => None,
+        });
+
+        match position {
             Some((r, c)) => {
+                let from = self.cursor();
                 self.view.goto(*r, *c);
+                self.record_jump(from);
 
                 self.status_line.save_history();
                 self.status_line.clear();
                 self.focus = Focus::View;
             }
-            None => self
-                .status_line
-                .set_error(AsciiLine::new(format!("{} not found", q)).map_err(Error::NotUnicode)?),
+            None => {
+                let path = reference.trim_start_matches('#').trim_end_matches('/');
+
+                let error = match self.doc.value.as_ref().map(|v| closest(v, path)) {
+                    Some((prefix, Some(key))) => {
+                        format!("{} not found, did you mean #{}/{}?", reference, prefix, key)
+                    }
+                    Some((prefix, None)) => {
+                        format!(
+                            "{} not found, #{} is the longest valid prefix",
+                            reference, prefix
+                        )
+                    }
+                    None => format!("{} not found", reference),
+                };
+
+                self.status_line.set_error(UnicodeLine::new(error));
+            }
         }
 
         Ok(())
     }
 }
 
+/// Parse the given JSON document into lines and index them. Documents with
+/// comments, trailing commas and the other extensions of JSON5 are accepted
+/// too, their comments are shown on the lines they belong to.
+fn load_json(input: &[u8], order: KeyOrder) -> Result<(Vec<JsonLine>, Document)> {
+    let (value, comments) = match serde_json::from_slice(input) {
+        Ok(value) => (value, vec![]),
+        Err(e) => match std::str::from_utf8(input).map(json5::document) {
+            Ok(Ok(document)) => document,
+
+            // the error that's further along is the real one, e.g. the JSON
+            // one is on the first comment of a document with comments
+            Ok(Err(e5)) if (e5.line, e5.column) > (e.line(), e.column()) => return Err(e5.into()),
+            _ => return Err(e.into()),
+        },
+    };
+
+    Ok(load_json_value(value, comments, order))
+}
+
+/// Render the given JSON document along with its comments and index it.
+fn load_json_value(
+    value: serde_json::Value,
+    comments: Vec<Comment>,
+    order: KeyOrder,
+) -> (Vec<JsonLine>, Document) {
+    let mut lines = parse_json(value.clone(), order);
+    add_comments(&mut lines, comments);
+    let doc = Document::new(value, &lines);
+
+    (lines, doc)
+}
+
+/// Parse the given newline delimited JSON into lines and index its records.
+fn load_ndjson(input: &[u8], order: KeyOrder) -> Result<(Vec<JsonLine>, Document)> {
+    let (lines, records) = parse_ndjson(utf8(input)?, order);
+    let doc = Document::records(records, &lines);
+
+    Ok((lines, doc))
+}
+
+/// Split the given text into lines, it's not a document so there's nothing to
+/// index.
+fn load_text(input: &[u8], _: KeyOrder) -> Result<(Vec<UnicodeLine<String>>, Document)> {
+    let lines = String::from_utf8_lossy(input)
+        .lines()
+        .map(|l| UnicodeLine::new(l.to_string()))
+        .collect();
+
+    Ok((lines, Document::default()))
+}
+
+/// Parse the given YAML into lines and index them.
+fn load_yaml(input: &[u8], order: KeyOrder) -> Result<(Vec<JsonLine>, Document)> {
+    let (docs, annotations) = yaml::documents(utf8(input)?)?;
+
+    Ok(load_documents(docs, annotations, order))
+}
+
+/// Parse the given TOML document into lines and index them.
+fn load_toml(input: &[u8], order: KeyOrder) -> Result<(Vec<JsonLine>, Document)> {
+    let (value, annotations) = toml::document(utf8(input)?)?;
+
+    Ok(load_documents(vec![value], annotations, order))
+}
+
+/// Parse the given MessagePack values into lines and index them.
+fn load_msgpack(input: &[u8], order: KeyOrder) -> Result<(Vec<JsonLine>, Document)> {
+    let (values, annotations) = msgpack::documents(input)?;
+
+    Ok(load_documents(values, annotations, order))
+}
+
+/// Parse the given CBOR items into lines and index them.
+fn load_cbor(input: &[u8], order: KeyOrder) -> Result<(Vec<JsonLine>, Document)> {
+    let (values, annotations) = cbor::documents(input)?;
+
+    Ok(load_documents(values, annotations, order))
+}
+
+/// Parse the given BSON documents into lines and index them.
+fn load_bson(input: &[u8], order: KeyOrder) -> Result<(Vec<JsonLine>, Document)> {
+    let (values, annotations) = bson::documents(input)?;
+
+    Ok(load_documents(values, annotations, order))
+}
+
+/// Render the documents converted from another format and index them, the
+/// annotations are applied before indexing. Many documents are shown like the
+/// records of NDJSON, i.e. each document is "#/<document>/...".
+fn load_documents(
+    mut docs: Vec<serde_json::Value>,
+    annotations: Vec<Annotation>,
+    order: KeyOrder,
+) -> (Vec<JsonLine>, Document) {
+    let many = docs.len() > 1;
+
+    let mut lines = docs
+        .iter()
+        .flat_map(|d| parse_json(d.clone(), order))
+        .collect::<Vec<_>>();
+    if lines.is_empty() {
+        lines = parse_json(serde_json::Value::Null, order);
+    }
+
+    if !annotations.is_empty() {
+        let (index, _) = if many {
+            index_records(&lines)
+        } else {
+            index(&lines)
+        };
+        annotate(&mut lines, &index, annotations);
+    }
+
+    let doc = if many {
+        Document::records(docs, &lines)
+    } else {
+        Document::new(docs.pop().unwrap_or(serde_json::Value::Null), &lines)
+    };
+
+    (lines, doc)
+}
+
+/// Render the documents parsed to detect their format and index them.
+fn load_parsed(parsed: Parsed, order: KeyOrder) -> (Vec<JsonLine>, Document) {
+    match parsed {
+        Parsed::Json(value, comments) => load_json_value(value, comments, order),
+        Parsed::Documents(docs, annotations) => load_documents(docs, annotations, order),
+    }
+}
+
+/// Detect the format of the given input and load it, the input parsed to
+/// detect its format isn't parsed again.
+fn json_open(input: &[u8], order: KeyOrder) -> Result<Opened<JsonLine>> {
+    let (format, parsed) = Format::detect_and_parse(input);
+    let load = json_loader(format);
+
+    let loaded = match parsed {
+        Some(parsed) => load_parsed(parsed, order),
+        None => load(input, order)?,
+    };
+
+    Ok((load, loaded))
+}
+
+/// Load the given input as text, whatever its format.
+fn text_open(input: &[u8], order: KeyOrder) -> Result<Opened<UnicodeLine<String>>> {
+    Ok((load_text, load_text(input, order)?))
+}
+
+/// The loader of the documents of the given format. Text is loaded as JSON, it
+/// might be a malformed document and the error tells what's wrong with it.
+fn json_loader(format: Format) -> Loader<JsonLine> {
+    match format {
+        Format::Json | Format::Text => load_json,
+        Format::Ndjson => load_ndjson,
+        Format::Yaml => load_yaml,
+        Format::Toml => load_toml,
+        Format::MessagePack => load_msgpack,
+        Format::Cbor => load_cbor,
+        Format::Bson => load_bson,
+    }
+}
+
+/// Get the reference under the cursor, if any.
+fn json_query(v: &View<JsonLine>) -> Option<String> {
+    let jt = v.current_line()?.token_at(v.col())?;
+    if jt.tag() != JsonTokenTag::Ref && jt.tag() != JsonTokenTag::Alias {
+        return None;
+    }
+
+    unquote(jt.text())
+}
+
+/// List the given completions along with the type of their values, the current
+/// one is between brackets.
+fn completions_hint(completions: &[Completion], current: Option<usize>) -> String {
+    completions
+        .iter()
+        .enumerate()
+        .map(|(i, c)| {
+            if Some(i) == current {
+                format!("[{}: {}]", c.segment, c.kind)
+            } else {
+                format!("{}: {}", c.segment, c.kind)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("  ")
+}
+
+/// Number of characters the given value takes in the first line it's shown on.
+fn value_width(value: &serde_json::Value) -> usize {
+    use serde_json::Value;
+
+    match value {
+        Value::Null => JsonToken::null().chars_count(),
+        Value::Bool(b) => JsonToken::bool(*b).chars_count(),
+        Value::Number(n) => JsonToken::number(n.clone()).chars_count(),
+        Value::String(s) => JsonToken::string(s.clone()).chars_count(),
+        Value::Array(_) | Value::Object(_) => 1,
+    }
+}
+
+/// Move the cursor of a read-only view like the help.
+fn navigate<L: Line>(view: &mut View<L>, ev: Key) {
+    match ev {
+        Key::Right | Key::Char('l') => view.move_right(),
+        Key::Left | Key::Char('h') => view.move_left(),
+        Key::Up | Key::Char('k') => view.move_up(),
+        Key::Down | Key::Char('j') => view.move_down(),
+        Key::Char('w') => view.move_to_next_word(),
+        Key::Char('b') => view.move_to_prev_word(),
+        Key::Char('e') => view.move_to_word_end(),
+        Key::Char('0') => view.move_to_sol(),
+        Key::Char('$') => view.move_to_eol(),
+        Key::Char('H') => view.move_to_screen_top(),
+        Key::Char('M') => view.move_to_screen_middle(),
+        Key::Char('L') => view.move_to_screen_bottom(),
+        Key::PageUp => view.page_up(),
+        Key::PageDown => view.page_down(),
+        Key::Ctrl('d') => view.half_page_down(),
+        Key::Ctrl('u') => view.half_page_up(),
+        Key::Ctrl('e') => view.scroll_down(),
+        Key::Ctrl('y') => view.scroll_up(),
+        _ => {}
+    }
+}
+
+/// Move the cursor of the view where the left button is clicked, or open the
+/// fold whose placeholder is clicked, and scroll with the wheel.
+fn mouse<L: Line>(view: &mut View<L>, button: MouseButton, (x, y): (u16, u16)) {
+    match button {
+        MouseButton::Left => match view.screen_position(x, y) {
+            Some(ScreenPosition::Char(r, c)) => view.goto(r, c),
+            Some(ScreenPosition::Fold(r)) => {
+                view.unfold(r);
+            }
+            None => {}
+        },
+        MouseButton::WheelUp => {
+            for _ in 0..WHEEL_ROWS {
+                view.scroll_up();
+            }
+        }
+        MouseButton::WheelDown => {
+            for _ in 0..WHEEL_ROWS {
+                view.scroll_down();
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Whether the file at the given path is meant to be a JSON document.
+fn has_json_extension(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| matches!(ext.to_lowercase().as_str(), "json" | "jsonc" | "json5"))
+}
+
+/// Read the whole input from the given path or from stdin if there's no path or
+/// if it's "-".
+fn read_input(path: Option<&Path>) -> Result<Vec<u8>> {
+    let mut input = vec![];
+
+    match path {
+        Some(p) if p != Path::new("-") => {
+            fs::File::open(p)?.read_to_end(&mut input)?;
+        }
+        _ => {
+            let stdin = io::stdin();
+            if termion::is_tty(&stdin) {
+                return Err(Error::NoInput);
+            }
+
+            stdin.lock().read_to_end(&mut input)?;
+        }
+    }
+
+    Ok(strip_bom(input))
+}
+
+/// Remove the UTF-8 byte order mark at the start of the input, if any, it's
+/// not part of the content and no loader expects it.
+fn strip_bom(mut input: Vec<u8>) -> Vec<u8> {
+    if input.starts_with(b"\xEF\xBB\xBF") {
+        input.drain(..3);
+    }
+
+    input
+}
+
+/// The input as text, the formats other than the binary ones must be UTF-8.
+fn utf8(input: &[u8]) -> Result<&str> {
+    std::str::from_utf8(input).map_err(|_| {
+        Error::Io(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "stream did not contain valid UTF-8",
+        ))
+    })
+}
+
+/// The (start, end) grapheme indices of the region of the given line to
+/// highlight for an error at the given 1 based byte column. It's the whole word
+/// if the error is on a word or right after it, like a misspelled `true`,
+/// otherwise it's a single character. An error past the end of the line is on
+/// its last character.
+fn error_region(line: &str, column: usize) -> (usize, usize) {
+    let mut byte = column.saturating_sub(1).min(line.len());
+    while !line.is_char_boundary(byte) {
+        byte -= 1;
+    }
+
+    let graphemes = line.graphemes(true).collect::<Vec<_>>();
+    let is_word = |g: &str| g.chars().all(|c| c.is_alphanumeric() || c == '_');
+
+    let mut start = line[..byte].graphemes(true).count();
+    if start >= graphemes.len() {
+        return (start.saturating_sub(1), start);
+    }
+
+    // the errors are often found on the character after the word
+    if !is_word(graphemes[start]) && start > 0 && is_word(graphemes[start - 1]) {
+        start -= 1;
+    }
+
+    let mut end = start + 1;
+    if is_word(graphemes[start]) {
+        while start > 0 && is_word(graphemes[start - 1]) {
+            start -= 1;
+        }
+        while end < graphemes.len() && is_word(graphemes[end]) {
+            end += 1;
+        }
+    }
+
+    (start, end)
+}
+
+/// Parse a fold command that is either "fold" optionally followed by the depth
+/// of the containers to fold or "unfold". Returns `Some(None)` to unfold
+/// everything.
+fn parse_fold(input: &str) -> Option<Option<usize>> {
+    let mut parts = input.split_whitespace();
+
+    let res = match parts.next()? {
+        "fold" => match parts.next() {
+            None => Some(0),
+            Some(d) => Some(d.parse().ok()?),
+        },
+        "unfold" => None,
+        _ => return None,
+    };
+
+    if parts.next().is_some() {
+        return None;
+    }
+
+    Some(res)
+}
+
+/// Build the regex to search the given pattern with. The search is case
+/// insensitive unless the pattern contains an uppercase character.
+fn search_regex(pattern: &str) -> std::result::Result<Regex, regex::Error> {
+    RegexBuilder::new(pattern)
+        .case_insensitive(!pattern.chars().any(char::is_uppercase))
+        .build()
+}
+
 fn parse_goto(input: &str) -> Option<(Option<usize>, Option<usize>)> {
     let mut parts = input.split(':').fuse();
 
@@ -445,19 +2019,69 @@ impl From<serde_json::Error> for Error {
     }
 }
 
+impl From<json5::Error> for Error {
+    fn from(e: json5::Error) -> Error {
+        Error::Json5(e)
+    }
+}
+
+impl From<yaml_rust2::ScanError> for Error {
+    fn from(e: yaml_rust2::ScanError) -> Error {
+        Error::Yaml(e)
+    }
+}
+
+impl From<toml::Error> for Error {
+    fn from(e: toml::Error) -> Error {
+        Error::Toml(e)
+    }
+}
+
+impl From<msgpack::Error> for Error {
+    fn from(e: msgpack::Error) -> Error {
+        Error::MessagePack(e)
+    }
+}
+
+impl From<cbor::Error> for Error {
+    fn from(e: cbor::Error) -> Error {
+        Error::Cbor(e)
+    }
+}
+
+impl From<bson::Error> for Error {
+    fn from(e: bson::Error) -> Error {
+        Error::Bson(e)
+    }
+}
+
 impl std::fmt::Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self {
             Error::Io(err) => err.fmt(f),
             Error::Json(err) => err.fmt(f),
-            Error::NotUnicode(s) => write!(f, "{} is not ascii", s),
+            Error::Json5(err) => err.fmt(f),
+            Error::Yaml(err) => err.fmt(f),
+            Error::Toml(err) => err.fmt(f),
+            Error::MessagePack(err) => err.fmt(f),
+            Error::Cbor(err) => err.fmt(f),
+            Error::Bson(err) => err.fmt(f),
+            Error::NoInput => write!(f, "no input file given and stdin is a terminal"),
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::parse_goto;
+    use serde_json::json;
+
+    use std::path::Path;
+
+    use super::{
+        error_region, has_json_extension, load_json, load_ndjson, load_toml, load_yaml, parse_fold,
+        parse_goto, search_regex, strip_bom, Error, Loader,
+    };
+    use jv::json::{JsonLine, KeyOrder};
 
     #[test]
     fn test_parse_goto() {
@@ -473,4 +2097,83 @@ mod tests {
         assert_eq!(parse_goto("1:yoyo"), None);
         assert_eq!(parse_goto("1:2:"), None);
     }
+
+    #[test]
+    fn test_error_region() {
+        assert_eq!(error_region("{\"a\": 1,}", 9), (8, 9));
+        assert_eq!(error_region("  \"a\": tru,", 9), (7, 10));
+        assert_eq!(error_region("  \"a\": tru,", 11), (7, 10));
+        assert_eq!(error_region("[1, 2", 6), (4, 5));
+        assert_eq!(error_region("\"è\" x", 6), (4, 5));
+        assert_eq!(error_region("", 0), (0, 0));
+    }
+
+    #[test]
+    fn test_load_with_bom() {
+        let load = |input: &[u8], load: Loader<JsonLine>| {
+            let (_, doc) = load(&strip_bom(input.to_vec()), KeyOrder::Sorted).unwrap();
+            doc.value.unwrap()
+        };
+
+        assert_eq!(load(b"\xEF\xBB\xBF{\"a\": 1}", load_json), json!({"a": 1}));
+        assert_eq!(
+            load(b"\xEF\xBB\xBF{\"a\": 1}\n{\"a\": 2}\n", load_ndjson),
+            json!([{"a": 1}, {"a": 2}])
+        );
+        assert_eq!(load(b"\xEF\xBB\xBFa: 1\n", load_yaml), json!({"a": 1}));
+        assert_eq!(load(b"\xEF\xBB\xBFa = 1\n", load_toml), json!({"a": 1}));
+
+        assert_eq!(strip_bom(b"\xEF\xBBa".to_vec()), b"\xEF\xBBa");
+    }
+
+    #[test]
+    fn test_load_json_error() {
+        // the comments are fine, the missing comma isn't
+        match load_json(b"// c\n{\"a\": 1 \"b\": 2}", KeyOrder::Sorted) {
+            Err(Error::Json5(e)) => assert_eq!((e.line, e.column), (2, 9)),
+            _ => panic!("expected a JSON5 error"),
+        }
+
+        match load_json(b"{\"a\": tru}", KeyOrder::Sorted) {
+            Err(Error::Json(e)) => assert_eq!((e.line(), e.column()), (1, 10)),
+            _ => panic!("expected a JSON error"),
+        }
+    }
+
+    #[test]
+    fn test_has_json_extension() {
+        assert!(has_json_extension(Path::new("a/b.json")));
+        assert!(has_json_extension(Path::new("tsconfig.JSONC")));
+        assert!(!has_json_extension(Path::new("a.yaml")));
+        assert!(!has_json_extension(Path::new("json")));
+    }
+
+    #[test]
+    fn test_parse_fold() {
+        assert_eq!(parse_fold("fold"), Some(Some(0)));
+        assert_eq!(parse_fold("fold 2"), Some(Some(2)));
+        assert_eq!(parse_fold("unfold"), Some(None));
+
+        assert_eq!(parse_fold("fold -1"), None);
+        assert_eq!(parse_fold("fold 1 2"), None);
+        assert_eq!(parse_fold("unfold 1"), None);
+        assert_eq!(parse_fold("folding"), None);
+        assert_eq!(parse_fold("42"), None);
+    }
+
+    #[test]
+    fn test_search_regex() {
+        let re = search_regex("hello").unwrap();
+        assert!(re.is_match("hello"));
+        assert!(re.is_match("HeLLo"));
+
+        let re = search_regex("Hello").unwrap();
+        assert!(re.is_match("Hello"));
+        assert!(!re.is_match("hello"));
+
+        let re = search_regex("[0-9]+ items?").unwrap();
+        assert!(re.is_match("42 ITEMS"));
+
+        assert!(search_regex("(unclosed").is_err());
+    }
 }
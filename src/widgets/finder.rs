@@ -0,0 +1,410 @@
+use std::io;
+use std::io::Write;
+
+use termion::clear;
+use termion::color;
+use termion::cursor;
+use termion::raw::RawTerminal;
+use termion::style;
+
+use unicode_width::UnicodeWidthChar;
+
+use crate::widgets::status_line::{StatusLine, StatusLineMode};
+use crate::widgets::Widget;
+
+/// An entry that can be picked with the `Finder`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FinderItem {
+    /// Reference of the value, like `#/items/0/name`.
+    pub path: String,
+
+    /// The value if it's a scalar, it's matched too only when the finder
+    /// includes the values.
+    pub value: Option<String>,
+}
+
+/// A picker that overlays the whole screen and fuzzy matches the typed pattern
+/// against a list of items showing the best matches first.
+#[derive(Debug)]
+pub struct Finder {
+    items: Vec<FinderItem>,
+
+    // indices into items of the ones matching the pattern, best first, along
+    // with the char indices of the matched chars
+    matches: Vec<(usize, Vec<usize>)>,
+
+    selected: usize,
+    frame_start: usize,
+    with_values: bool,
+
+    input: StatusLine,
+
+    width: u16,
+    height: u16,
+}
+
+impl Finder {
+    /// Create a new `Finder` over the given items that takes the whole terminal
+    /// of the given size.
+    pub fn new(size: (u16, u16), items: Vec<FinderItem>) -> Self {
+        let mut input = StatusLine::new(size.1.saturating_sub(2), size.0);
+        input.activate(StatusLineMode::Find);
+
+        let mut finder = Finder {
+            items,
+            matches: vec![],
+            selected: 0,
+            frame_start: 0,
+            with_values: false,
+            input,
+            width: size.0,
+            height: size.1,
+        };
+        finder.update_matches();
+
+        finder
+    }
+
+    /// Change the size of the finder.
+    pub fn resize(&mut self, size: (u16, u16)) {
+        self.width = size.0;
+        self.height = size.1;
+        self.input.resize(size.1.saturating_sub(2), size.0);
+
+        self.scroll_to_selected();
+    }
+
+    /// The pattern the items are matched against.
+    pub fn pattern(&self) -> &str {
+        self.input.text()
+    }
+
+    /// The selected item, if any item matches.
+    pub fn selected(&self) -> Option<&FinderItem> {
+        self.matches
+            .get(self.selected)
+            .map(|(ix, _)| &self.items[*ix])
+    }
+
+    pub fn insert(&mut self, c: char) {
+        self.input.insert(c);
+        self.update_matches();
+    }
+
+    pub fn remove(&mut self) {
+        // never remove the char that marks the mode
+        if self.input.at_start() {
+            return;
+        }
+
+        self.input.remove();
+        self.update_matches();
+    }
+
+    pub fn left(&mut self) {
+        self.input.left();
+    }
+
+    pub fn right(&mut self) {
+        self.input.right();
+    }
+
+    /// Select the previous, i.e. better, match.
+    pub fn up(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+        self.scroll_to_selected();
+    }
+
+    /// Select the next, i.e. worse, match.
+    pub fn down(&mut self) {
+        if self.selected + 1 < self.matches.len() {
+            self.selected += 1;
+        }
+
+        self.scroll_to_selected();
+    }
+
+    /// Toggle whether the scalar values are matched along with the paths.
+    pub fn toggle_values(&mut self) {
+        self.with_values = !self.with_values;
+        self.update_matches();
+    }
+
+    /// Show a preview of the selected value.
+    pub fn set_preview(&mut self, preview: Option<String>) {
+        self.input.set_hint(preview);
+    }
+
+    fn item_text(&self, item: &FinderItem) -> String {
+        match (&item.value, self.with_values) {
+            (Some(v), true) => format!("{} {}", item.path, v),
+            _ => item.path.clone(),
+        }
+    }
+
+    fn update_matches(&mut self) {
+        let pattern = self.pattern();
+
+        let mut matches = self
+            .items
+            .iter()
+            .enumerate()
+            .filter_map(|(i, item)| {
+                let text = self.item_text(item);
+                let (score, positions) = fuzzy_match(pattern, &text)?;
+
+                Some((score, text.len(), i, positions))
+            })
+            .collect::<Vec<_>>();
+
+        // best score first, then the shortest and then in document order
+        matches.sort_by_key(|(score, len, i, _)| (-score, *len, *i));
+
+        self.matches = matches
+            .into_iter()
+            .map(|(_, _, i, positions)| (i, positions))
+            .collect();
+        self.selected = 0;
+        self.frame_start = 0;
+
+        self.update_counter();
+    }
+
+    fn scroll_to_selected(&mut self) {
+        let list_height = self.list_height().max(1);
+
+        if self.selected < self.frame_start {
+            self.frame_start = self.selected;
+        } else if self.selected >= self.frame_start + list_height {
+            self.frame_start = self.selected + 1 - list_height;
+        }
+
+        self.update_counter();
+    }
+
+    fn update_counter(&mut self) {
+        let current = Some(self.selected).filter(|s| *s < self.matches.len());
+        self.input.set_matches(Some((current, self.matches.len())));
+    }
+
+    // the bottom two rows are taken by the input
+    fn list_height(&self) -> usize {
+        usize::from(self.height.saturating_sub(2))
+    }
+}
+
+impl Widget for Finder {
+    fn render(&self, term: &mut RawTerminal<impl io::Write>) -> io::Result<()> {
+        let bg = color::Bg(color::AnsiValue::grayscale(4));
+        let selected_bg = color::Bg(color::AnsiValue::grayscale(6));
+
+        write!(term, "{}", cursor::Hide)?;
+
+        for i in 0..self.list_height() {
+            let m = self.frame_start + i;
+
+            write!(
+                term,
+                "{}{}{}{}",
+                cursor::Goto(1, i as u16 + 1),
+                color::Fg(color::Reset),
+                if m == self.selected { selected_bg } else { bg },
+                clear::CurrentLine,
+            )?;
+
+            let (ix, positions) = match self.matches.get(m) {
+                Some(m) => m,
+                None => continue,
+            };
+
+            let item = &self.items[*ix];
+            let path_len = item.path.chars().count();
+
+            let mut width = 0;
+            let mut positions = positions.iter().peekable();
+            for (ci, c) in self.item_text(item).chars().enumerate() {
+                width += c.width().unwrap_or(0);
+                if width > usize::from(self.width) {
+                    break;
+                }
+
+                let fg = if ci >= path_len {
+                    color::Fg(color::LightGreen).to_string()
+                } else {
+                    color::Fg(color::Reset).to_string()
+                };
+
+                if positions.next_if_eq(&&ci).is_some() {
+                    write!(
+                        term,
+                        "{}{}{}{}",
+                        color::Fg(color::Yellow),
+                        style::Bold,
+                        c,
+                        style::NoBold
+                    )?;
+                } else {
+                    write!(term, "{}{}", fg, c)?;
+                }
+            }
+        }
+
+        write!(
+            term,
+            "{}{}",
+            color::Fg(color::Reset),
+            color::Bg(color::Reset)
+        )?;
+        self.input.render(term)?;
+
+        write!(term, "{}", cursor::Show)?;
+        term.flush()
+    }
+
+    fn focus(&self, term: &mut RawTerminal<impl io::Write>) -> io::Result<()> {
+        self.input.focus(term)
+    }
+}
+
+/// Match the pattern against the given text, all the chars of the pattern must
+/// appear in the text in the same order. Returns the score of the match, the
+/// higher the better, and the char indices of the matched chars. Matches are
+/// case insensitive unless the pattern contains uppercase chars.
+pub fn fuzzy_match(pattern: &str, text: &str) -> Option<(i64, Vec<usize>)> {
+    let case_sensitive = pattern.chars().any(char::is_uppercase);
+    let normalize = |c: char| {
+        if case_sensitive {
+            c
+        } else {
+            c.to_lowercase().next().unwrap_or(c)
+        }
+    };
+
+    let pattern = pattern.chars().map(normalize).collect::<Vec<_>>();
+    let text = text.chars().map(normalize).collect::<Vec<_>>();
+
+    if pattern.is_empty() {
+        return Some((0, vec![]));
+    }
+
+    // find where the leftmost match ends and then go backwards from there to
+    // find the shortest match that ends there
+    let mut pi = 0;
+    let end = text.iter().position(|c| {
+        if *c == pattern[pi] {
+            pi += 1;
+        }
+
+        pi == pattern.len()
+    })?;
+
+    let mut positions = Vec::with_capacity(pattern.len());
+    for i in (0..=end).rev() {
+        if text[i] == pattern[pattern.len() - positions.len() - 1] {
+            positions.push(i);
+
+            if positions.len() == pattern.len() {
+                break;
+            }
+        }
+    }
+    positions.reverse();
+
+    let mut score = 0;
+    for (n, &i) in positions.iter().enumerate() {
+        score += 16;
+
+        // matches at the start of keys or words are likely intended
+        if i == 0 || matches!(text[i - 1], '/' | '_' | '-' | '.' | ' ') {
+            score += 8;
+        }
+
+        if n > 0 {
+            let gap = i - positions[n - 1] - 1;
+            if gap == 0 {
+                score += 8;
+            } else {
+                score -= gap.min(8) as i64;
+            }
+        }
+    }
+
+    Some((score, positions))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{fuzzy_match, Finder, FinderItem};
+
+    fn item(path: &str, value: Option<&str>) -> FinderItem {
+        FinderItem {
+            path: path.to_string(),
+            value: value.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_fuzzy_match() {
+        assert_eq!(fuzzy_match("", "#/a"), Some((0, vec![])));
+        assert_eq!(fuzzy_match("x", "#/a"), None);
+        assert_eq!(fuzzy_match("ab", "ba"), None);
+
+        assert_eq!(fuzzy_match("name", "#/a/Name").unwrap().1, vec![4, 5, 6, 7]);
+        assert_eq!(fuzzy_match("Name", "#/a/name"), None);
+
+        // the shortest match is preferred
+        assert_eq!(fuzzy_match("ab", "a-a-ab").unwrap().1, vec![4, 5]);
+
+        let (contiguous, _) = fuzzy_match("name", "#/items/0/name").unwrap();
+        let (scattered, _) = fuzzy_match("name", "#/n/a/m/e").unwrap();
+        let (inside, _) = fuzzy_match("name", "#/surname").unwrap();
+        assert!(contiguous > scattered);
+        assert!(contiguous > inside);
+    }
+
+    #[test]
+    fn test_finder() {
+        let mut finder = Finder::new(
+            (80, 10),
+            vec![
+                item("#", None),
+                item("#/items", None),
+                item("#/items/0", None),
+                item("#/items/0/name", Some("\"foo\"")),
+                item("#/name", Some("\"bar\"")),
+            ],
+        );
+
+        assert_eq!(finder.matches.len(), 5);
+        assert_eq!(finder.selected().unwrap().path, "#");
+
+        for c in "name".chars() {
+            finder.insert(c);
+        }
+        assert_eq!(finder.pattern(), "name");
+        assert_eq!(finder.selected().unwrap().path, "#/name");
+
+        finder.down();
+        assert_eq!(finder.selected().unwrap().path, "#/items/0/name");
+        finder.down();
+        assert_eq!(finder.selected().unwrap().path, "#/items/0/name");
+        finder.up();
+        finder.up();
+        assert_eq!(finder.selected().unwrap().path, "#/name");
+
+        for c in " foo".chars() {
+            finder.insert(c);
+        }
+        assert_eq!(finder.selected(), None);
+
+        finder.toggle_values();
+        assert_eq!(finder.selected().unwrap().path, "#/items/0/name");
+        assert_eq!(finder.matches.len(), 1);
+
+        for _ in 0..10 {
+            finder.remove();
+        }
+        assert_eq!(finder.pattern(), "");
+        assert_eq!(finder.matches.len(), 5);
+    }
+}
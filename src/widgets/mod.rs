@@ -1,5 +1,6 @@
-pub mod ascii_line;
+pub mod finder;
 pub mod status_line;
+pub mod unicode_line;
 pub mod view;
 
 use std::io;
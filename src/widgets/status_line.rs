@@ -1,4 +1,4 @@
-use crate::widgets::ascii_line::AsciiLine;
+use crate::widgets::unicode_line::UnicodeLine;
 use crate::widgets::view::Line;
 use crate::widgets::Widget;
 
@@ -9,10 +9,13 @@ use termion::color;
 use termion::cursor;
 use termion::raw::RawTerminal;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum StatusLineMode {
     Query,
     Command,
+    Search,
+    ReverseSearch,
+    Find,
 }
 
 #[derive(Debug)]
@@ -26,13 +29,27 @@ pub struct StatusLine {
 
     width: u16,
 
-    buffer: AsciiLine<String>,
+    buffer: UnicodeLine<String>,
     mode: StatusLineMode,
 
-    error: Option<AsciiLine<String>>,
+    error: Option<UnicodeLine<String>>,
+    info: Option<UnicodeLine<String>>,
+
+    // index of the current search match, if any, and total number of matches
+    matches: Option<(Option<usize>, usize)>,
+
+    // index of the record under the cursor, if any, and total number of
+    // records
+    record: Option<(Option<usize>, usize)>,
+
+    // reference of the value under the cursor
+    path: Option<String>,
+
+    // shown instead of the path while editing, like the completions of a query
+    hint: Option<String>,
 
     // history is per mode
-    history: Vec<Vec<AsciiLine<String>>>,
+    history: Vec<Vec<UnicodeLine<String>>>,
     history_t: Option<usize>,
 }
 
@@ -46,12 +63,25 @@ impl StatusLine {
             mode: StatusLineMode::Command,
             width,
             error: None,
-            buffer: AsciiLine::new(String::new()).unwrap(),
-            history: vec![vec![], vec![]],
+            info: None,
+            matches: None,
+            record: None,
+            path: None,
+            hint: None,
+            buffer: UnicodeLine::new(String::new()),
+            history: vec![vec![], vec![], vec![], vec![]],
             history_t: None,
         }
     }
 
+    /// Move the status line to the given row and change its width.
+    pub fn resize(&mut self, cursor_row: u16, width: u16) {
+        self.cursor_row = cursor_row;
+        self.width = width;
+
+        self.center_horizontally();
+    }
+
     pub fn text(&self) -> &str {
         &self.buffer.line()[1..]
     }
@@ -67,14 +97,13 @@ impl StatusLine {
         match self.mode {
             StatusLineMode::Command => self.insert(':'),
             StatusLineMode::Query => self.insert('#'),
+            StatusLineMode::Search => self.insert('/'),
+            StatusLineMode::ReverseSearch => self.insert('?'),
+            StatusLineMode::Find => self.insert('>'),
         }
     }
 
     pub fn insert(&mut self, c: char) {
-        if !c.is_ascii() {
-            return;
-        }
-
         self.buffer.insert(self.col_char_ix, c);
         self.right();
     }
@@ -90,16 +119,64 @@ impl StatusLine {
         self.frame_start_col = 0;
         self.col_char_ix = 0;
         self.error = None;
+        self.info = None;
         self.history_t = None;
+        self.hint = None;
         self.mode = StatusLineMode::Command;
     }
 
-    pub fn set_error(&mut self, error: AsciiLine<String>) {
+    /// Replace the text being edited and move the cursor to its end.
+    pub fn set_text(&mut self, text: &str) {
+        let mut line = self.buffer.line().chars().take(1).collect::<String>();
+        line.push_str(text);
+
+        self.buffer = UnicodeLine::new(line);
+        self.col_char_ix = self.buffer.chars_count();
+        self.center_horizontally();
+    }
+
+    pub fn set_error(&mut self, error: UnicodeLine<String>) {
         self.error = Some(error);
     }
 
-    pub fn no_error(&mut self) {
+    /// Show an informative message until the next call to `no_messages`.
+    pub fn set_info(&mut self, info: UnicodeLine<String>) {
+        self.info = Some(info);
+    }
+
+    pub fn no_messages(&mut self) {
         self.error = None;
+        self.info = None;
+    }
+
+    /// Show the given reference of the value under the cursor or nothing if
+    /// `None`.
+    pub fn set_path(&mut self, path: Option<String>) {
+        self.path = path;
+    }
+
+    /// Show the given hint in place of the reference of the value under the
+    /// cursor until it's reset with `None` or the status line is cleared.
+    pub fn set_hint(&mut self, hint: Option<String>) {
+        self.hint = hint;
+    }
+
+    /// Show the index of the current search match, if any, and the total
+    /// number of matches or nothing if `None`.
+    pub fn set_matches(&mut self, matches: Option<(Option<usize>, usize)>) {
+        self.matches = matches;
+    }
+
+    /// Show the index of the record under the cursor, if any, and the total
+    /// number of records or nothing if `None`.
+    pub fn set_record(&mut self, record: Option<(Option<usize>, usize)>) {
+        self.record = record;
+    }
+
+    /// Whether the cursor is right after the char that marks the mode, i.e. at
+    /// the start of the text.
+    pub fn at_start(&self) -> bool {
+        self.col_char_ix <= 1
     }
 
     pub fn is_empty(&self) -> bool {
@@ -122,8 +199,8 @@ impl StatusLine {
 
     pub fn history_down(&mut self) {
         match self.history_t {
-            None => return,
-            Some(i) if i >= self.history[self.mode.id()].len() => return,
+            None => {}
+            Some(i) if i >= self.history[self.mode.id()].len() => {}
             Some(i) if i + 1 >= self.history[self.mode.id()].len() => {
                 // reset buffer
                 self.activate(self.mode);
@@ -190,8 +267,10 @@ impl StatusLine {
 impl Widget for StatusLine {
     fn render(&self, term: &mut RawTerminal<impl io::Write>) -> io::Result<()> {
         let mode_line = match self.mode {
-            StatusLineMode::Command => AsciiLine::new(" NORMAL ").unwrap(),
-            StatusLineMode::Query => AsciiLine::new(" QUERY ").unwrap(),
+            StatusLineMode::Command => UnicodeLine::new(" NORMAL "),
+            StatusLineMode::Query => UnicodeLine::new(" QUERY "),
+            StatusLineMode::Search | StatusLineMode::ReverseSearch => UnicodeLine::new(" SEARCH "),
+            StatusLineMode::Find => UnicodeLine::new(" FIND "),
         };
 
         writeln!(
@@ -207,10 +286,77 @@ impl Widget for StatusLine {
             color::Fg(color::Reset),
         )?;
 
+        let record = self.record.map(|(current, total)| match current {
+            Some(i) => format!("record {}/{} ", i + 1, total),
+            None => format!("record -/{} ", total),
+        });
+        let matches = self.matches.map(|(current, total)| match current {
+            Some(i) => format!("{}/{} ", i + 1, total),
+            None => format!("-/{} ", total),
+        });
+
+        let counters = match (record, matches) {
+            (None, None) => None,
+            (record, matches) => Some(format!(
+                "{}{}",
+                record.unwrap_or_default(),
+                matches.unwrap_or_default()
+            )),
+        };
+        let counters_len = counters.as_ref().map_or(0, String::len);
+
+        if let Some(path) = self.hint.as_ref().or(self.path.as_ref()) {
+            // show the end of the path if it's too long because it's the most
+            // interesting part, while it's the beginning for hints
+            let available =
+                usize::from(self.width).saturating_sub(mode_line.chars_count() + counters_len + 1);
+            let path_len = path.chars().count();
+
+            let path = if path_len <= available {
+                path.clone()
+            } else if available == 0 {
+                String::new()
+            } else if self.hint.is_some() {
+                let mut p = path.chars().take(available - 1).collect::<String>();
+                p.push('…');
+                p
+            } else {
+                let mut p = "…".to_string();
+                p.extend(path.chars().skip(path_len - available + 1));
+                p
+            };
+
+            write!(
+                term,
+                "{}{}{}{}{}{}",
+                cursor::Goto(mode_line.chars_count() as u16 + 1, self.cursor_row + 1),
+                color::Bg(color::AnsiValue::grayscale(6)),
+                color::Fg(color::Black),
+                path,
+                color::Bg(color::Reset),
+                color::Fg(color::Reset),
+            )?;
+        }
+
+        if let Some(counters) = counters {
+            if counters_len + mode_line.chars_count() < usize::from(self.width) {
+                write!(
+                    term,
+                    "{}{}{}{}{}{}",
+                    cursor::Goto(self.width - counters_len as u16 + 1, self.cursor_row + 1),
+                    color::Bg(color::AnsiValue::grayscale(6)),
+                    color::Fg(color::Black),
+                    counters,
+                    color::Bg(color::Reset),
+                    color::Fg(color::Reset),
+                )?;
+            }
+        }
+
         let goto_line = cursor::Goto(1, self.cursor_row + 2);
 
-        match &self.error {
-            Some(error) => {
+        match (&self.error, &self.info) {
+            (Some(error), _) => {
                 write!(
                     term,
                     "{}{}{}{}{}",
@@ -221,7 +367,18 @@ impl Widget for StatusLine {
                     error.render(0, usize::from(self.width)),
                 )?;
             }
-            None => {
+            (None, Some(info)) => {
+                write!(
+                    term,
+                    "{}{}{}{}{}",
+                    goto_line,
+                    color::Bg(color::AnsiValue::grayscale(4)),
+                    color::Fg(color::LightGreen),
+                    clear::CurrentLine,
+                    info.render(0, usize::from(self.width)),
+                )?;
+            }
+            (None, None) => {
                 write!(
                     term,
                     "{}{}{}{}{}",
@@ -253,6 +410,8 @@ impl StatusLineMode {
         match self {
             StatusLineMode::Query => 0,
             StatusLineMode::Command => 1,
+            StatusLineMode::Search | StatusLineMode::ReverseSearch => 2,
+            StatusLineMode::Find => 3,
         }
     }
 }
@@ -1,72 +1,81 @@
+use std::borrow::Cow;
 use std::collections::BTreeMap;
 
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
 use crate::widgets::view::Line;
 
-/// Simple ascii line that can be used to create a simple viewer over ascii
-/// text.
+/// Line of unicode text that can be used to create a simple viewer over any
+/// text. Each grapheme cluster is a single character as far as the cursor is
+/// concerned and it's as wide as the terminal shows it, for example East Asian
+/// ideographs take two columns.
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub struct AsciiLine<S> {
+pub struct UnicodeLine<S> {
     l: S,
+
+    // byte offset of the start of each grapheme cluster
+    graphemes: Vec<usize>,
+
+    // widths of the grapheme clusters that are not 1 column wide
     char_widths: BTreeMap<usize, u8>,
     first_col: usize,
 }
 
-impl<S> AsciiLine<S>
+impl<S> UnicodeLine<S>
 where
     S: AsRef<str> + std::fmt::Debug,
 {
-    /// Create a new AsciiLine from the given string. Returns the raw line on
-    /// error if it contains non ascii characters.
-    pub fn new(l: S) -> Result<Self, S> {
-        if l.as_ref().is_ascii() {
-            let mut line = AsciiLine {
-                l,
-                char_widths: BTreeMap::new(),
-                first_col: 0,
-            };
+    pub fn new(l: S) -> Self {
+        let mut line = UnicodeLine {
+            l,
+            graphemes: vec![],
+            char_widths: BTreeMap::new(),
+            first_col: 0,
+        };
 
-            line.indent(0);
+        line.indent(0);
 
-            Ok(line)
-        } else {
-            Err(l)
-        }
+        line
     }
 
     pub fn line(&self) -> &S {
         &self.l
     }
+
+    fn grapheme(&self, idx: usize) -> &str {
+        let l = self.l.as_ref();
+        let end = self.graphemes.get(idx + 1).cloned().unwrap_or(l.len());
+
+        &l[self.graphemes[idx]..end]
+    }
 }
 
-impl AsciiLine<String> {
+impl UnicodeLine<String> {
     pub fn clear(&mut self) {
         self.l.clear();
+        self.graphemes.clear();
         self.char_widths.clear();
     }
 
     pub fn insert(&mut self, ix: usize, c: char) {
-        self.l.insert(ix, c);
+        let offset = self.graphemes.get(ix).cloned().unwrap_or(self.l.len());
+        self.l.insert(offset, c);
 
-        // there's no need to recalculate tab widths if we're not inserting any
-        // tabs or we haven't any already because we're certain that nothing
-        // would change.
-        if c == '\t' || !self.char_widths.is_empty() {
-            self.indent(self.first_col);
-        }
+        // the new character might be merged with the ones around it, like
+        // combining accents, so the grapheme clusters must be found again.
+        self.indent(self.first_col);
     }
 
     pub fn remove(&mut self, ix: usize) {
-        self.l.remove(ix);
+        let end = self.graphemes.get(ix + 1).cloned().unwrap_or(self.l.len());
+        self.l.replace_range(self.graphemes[ix]..end, "");
 
-        // if the line doesn't have any characters with width >1 then there's no
-        // need to recalculate anything.
-        if !self.char_widths.is_empty() {
-            self.indent(self.first_col);
-        }
+        self.indent(self.first_col);
     }
 }
 
-impl<S> Line for AsciiLine<S>
+impl<S> Line for UnicodeLine<S>
 where
     S: AsRef<str> + std::fmt::Debug,
 {
@@ -74,65 +83,85 @@ where
         let mut w = 0;
         let mut rendered = String::new();
 
-        for (i, c) in self.l.as_ref().chars().enumerate().skip(start_col) {
+        for i in start_col..self.chars_count() {
             w += usize::from(self.char_width(i));
 
             if w > width {
                 break;
             }
 
-            rendered.push(c);
+            rendered.push_str(self.grapheme(i));
         }
 
         rendered
     }
 
     fn indent(&mut self, first_col: usize) {
-        // This is inefficient, but hopefully this hasn't to be called too often
-        // because characters with widths >1 are not that common...
-
+        self.graphemes.clear();
         self.char_widths.clear();
         self.first_col = first_col;
 
         let mut col = first_col;
-        for (i, c) in self.l.as_ref().chars().enumerate() {
-            if c == '\t' {
-                let tw = 8 - (col % 8) as u8;
+        for (i, (offset, g)) in self.l.as_ref().grapheme_indices(true).enumerate() {
+            self.graphemes.push(offset);
 
-                self.char_widths.insert(i, tw);
-                col += usize::from(tw);
+            // zero width clusters, like a lone zero width space, still take a
+            // column otherwise the cursor couldn't be placed on them.
+            let w = if g == "\t" {
+                8 - (col % 8) as u8
             } else {
-                col += 1;
+                g.width().clamp(1, usize::from(u8::MAX)) as u8
+            };
+
+            if w != 1 {
+                self.char_widths.insert(i, w);
             }
+
+            col += usize::from(w);
         }
     }
 
     fn chars_count(&self) -> usize {
-        self.l.as_ref().len()
+        self.graphemes.len()
     }
 
     fn char_width(&self, idx: usize) -> u16 {
         u16::from(*self.char_widths.get(&idx).unwrap_or(&1))
     }
+
+    fn text(&self) -> Cow<'_, str> {
+        Cow::Borrowed(self.l.as_ref())
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::AsciiLine;
+    use super::UnicodeLine;
     use crate::widgets::view::Line;
 
     #[test]
-    fn test_returns_error_for_non_ascii_string() {
-        assert_eq!(AsciiLine::new("la vita è bella"), Err("la vita è bella"));
-        assert_eq!(
-            AsciiLine::new("❤️ pugs ❤️"),
-            Err("❤️ pugs ❤️")
-        );
+    fn test_unicode() {
+        let line = UnicodeLine::new("la vita e\u{301} bella");
+        assert_eq!(line.chars_count(), 15);
+        assert_eq!(line.render(8, 3), "e\u{301} b");
+
+        let line = UnicodeLine::new("日本語 ok");
+        assert_eq!(line.chars_count(), 6);
+        assert_eq!(line.char_width(0), 2);
+        assert_eq!(line.char_width(3), 1);
+        assert_eq!(line.render(0, 3), "日");
+        assert_eq!(line.render(1, 80), "本語 ok");
+
+        let line = UnicodeLine::new("❤️ pugs 👨‍👩‍👧");
+        assert_eq!(line.chars_count(), 8);
+        assert_eq!(line.char_width(0), 2);
+        assert_eq!(line.char_width(7), 2);
+        assert_eq!(line.render(0, 7), "❤️ pugs");
     }
 
     #[test]
     fn test_renders_correctly() {
-        let line = AsciiLine::new("42 is the answer to life, the universe and everything").unwrap();
+        let line = UnicodeLine::new("42 is the answer to life, the universe and everything");
 
         assert_eq!(line.chars_count(), 53);
 
@@ -161,7 +190,7 @@ mod tests {
 
     #[test]
     fn test_renders_tabs_correctly() {
-        let mut line = AsciiLine::new("\tA\tBB\tCCC\tDDDD\tEEEEE\tFFFFFF\tGGGGGGG\tH").unwrap();
+        let mut line = UnicodeLine::new("\tA\tBB\tCCC\tDDDD\tEEEEE\tFFFFFF\tGGGGGGG\tH");
 
         assert_eq!(line.chars_count(), 37);
 
@@ -225,7 +254,7 @@ mod tests {
 
     #[test]
     fn test_insert() {
-        let mut line = AsciiLine::new("".to_string()).unwrap();
+        let mut line = UnicodeLine::new("".to_string());
 
         line.insert(0, 'h');
         line.insert(1, 'i');
@@ -247,7 +276,7 @@ mod tests {
 
     #[test]
     fn test_remove() {
-        let mut line = AsciiLine::new("hi, ground!".to_string()).unwrap();
+        let mut line = UnicodeLine::new("hi, ground!".to_string());
 
         line.remove(2);
         line.remove(2);
@@ -257,9 +286,26 @@ mod tests {
         assert_eq!(line.render(0, 80), "higrund");
     }
 
+    #[test]
+    fn test_edit_unicode() {
+        let mut line = UnicodeLine::new("caf".to_string());
+
+        line.insert(3, 'e');
+        line.insert(4, '\u{301}');
+        assert_eq!(line.chars_count(), 4);
+        assert_eq!(line.render(0, 80), "cafe\u{301}");
+
+        line.insert(0, '日');
+        assert_eq!(line.char_width(0), 2);
+        assert_eq!(line.render(0, 3), "日c");
+
+        line.remove(4);
+        assert_eq!(line.render(0, 80), "日caf");
+    }
+
     #[test]
     fn test_edit() {
-        let mut line = AsciiLine::new("".to_string()).unwrap();
+        let mut line = UnicodeLine::new("".to_string());
 
         line.insert(0, '/');
         line.remove(0);
@@ -286,7 +332,7 @@ mod tests {
 
     #[test]
     fn test_edit_tabs() {
-        let mut line = AsciiLine::new("".to_string()).unwrap();
+        let mut line = UnicodeLine::new("".to_string());
 
         line.insert(0, '0');
         line.insert(1, '\t');
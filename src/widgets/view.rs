@@ -1,3 +1,5 @@
+use std::borrow::Cow;
+use std::collections::BTreeMap;
 use std::io;
 use std::io::Write;
 
@@ -5,9 +7,17 @@ use termion::clear;
 use termion::color;
 use termion::cursor;
 use termion::raw::RawTerminal;
+use termion::style;
+
+use regex::Regex;
+use unicode_segmentation::UnicodeSegmentation;
 
 use crate::widgets::Widget;
 
+/// Minimum number of rows of a view, the terminal can be made smaller than
+/// that but the cursor needs some room to move.
+const MIN_HEIGHT: u16 = 2;
+
 /// `Line` is a line that can be rendered by a `View`.
 pub trait Line {
     /// Render this line starting from the given column spanning for a given
@@ -17,8 +27,8 @@ pub trait Line {
 
     /// Return the number of the visible characters that compose the string.
     /// This function must not take into account the markup that's added into
-    /// the rendered string nor the character width. As of now, only ASCII
-    /// characters are supported because Unicode is hard to get right.
+    /// the rendered string nor the character width. A character is a grapheme
+    /// cluster, that is what the user perceives as a single character.
     fn chars_count(&self) -> usize;
 
     /// Return the number of columns the char at the given positions spans.
@@ -28,12 +38,57 @@ pub trait Line {
     /// indentation doesn't require the line to put spaces at the beginning, but
     /// it must update its tabs width.
     fn indent(&mut self, first_col: usize);
+
+    /// Return the text of the line without any markup.
+    fn text(&self) -> Cow<'_, str>;
+
+    /// Return the (start, end) char indices, end excluded, of the words the
+    /// word motions move between. By default, like in vim, a word is either a
+    /// sequence of letters, digits and underscores or a sequence of other non
+    /// blank characters.
+    fn words(&self) -> Vec<(usize, usize)> {
+        let class = |g: &str| {
+            if g.chars().all(char::is_whitespace) {
+                0
+            } else if g.chars().all(|c| c.is_alphanumeric() || c == '_') {
+                1
+            } else {
+                2
+            }
+        };
+
+        let mut words: Vec<(usize, usize)> = vec![];
+        let mut prev_class = 0;
+
+        for (i, g) in self.text().graphemes(true).enumerate() {
+            let class = class(g);
+
+            match words.last_mut() {
+                Some((_, end)) if class == prev_class && *end == i => *end += 1,
+                _ if class != 0 => words.push((i, i + 1)),
+                _ => {}
+            }
+
+            prev_class = class;
+        }
+
+        words
+    }
 }
 
 /// A read-only view over some lines.
 pub struct View<L> {
     lines: Vec<L>,
 
+    // rows of the lines that are not hidden inside a fold, all the fields
+    // about the frame and the cursor refer to indices into this vector.
+    rows: Vec<usize>,
+    folds: BTreeMap<usize, Fold>,
+
+    // (row, start_char_ix, end_char_ix) of the search matches sorted by
+    // position.
+    matches: Vec<(usize, usize, usize)>,
+
     width: u16,
     height: u16,
     num_lines_padding: usize,
@@ -49,6 +104,25 @@ pub struct View<L> {
     cursor_col: u16,
 }
 
+/// What is shown at a given position on screen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScreenPosition {
+    /// The (row, char index) of a char of a line, the positions after the end
+    /// of the line are mapped to its last char.
+    Char(usize, usize),
+
+    /// The placeholder of the fold that starts at the given row.
+    Fold(usize),
+}
+
+/// A fold hides all the lines after `start` up to `end` included and shows a
+/// placeholder after the `start` line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Fold {
+    end: usize,
+    placeholder: String,
+}
+
 impl<L> View<L>
 where
     L: Line,
@@ -60,6 +134,9 @@ where
         let num_lines_padding = lines.len().to_string().len();
 
         let mut view = View {
+            rows: (0..lines.len()).collect(),
+            folds: BTreeMap::new(),
+            matches: vec![],
             lines,
             num_lines_padding,
             cursor_col: 0,
@@ -67,7 +144,7 @@ where
             line_char_ix: 0,
             frame_start_char_ix: 0,
             frame_start_row: 0,
-            height: size.1,
+            height: size.1.max(MIN_HEIGHT),
             max_line_char_ix: 0,
             width: size.0,
         };
@@ -82,6 +159,32 @@ where
         view
     }
 
+    /// Replace the lines shown by the view, all the folds and search matches
+    /// are removed and the cursor is moved to the beginning.
+    pub fn set_lines(&mut self, lines: impl IntoIterator<Item = L>) {
+        *self = View::new((self.width, self.height), lines);
+    }
+
+    /// Change the size of the view keeping the cursor on the same line and, if
+    /// possible, at the same position on screen.
+    pub fn resize(&mut self, size: (u16, u16)) {
+        let r = self.current_visible_row();
+
+        self.width = size.0;
+        self.height = size.1.max(MIN_HEIGHT);
+
+        let cursor_row = usize::from(self.cursor_row)
+            .min(usize::from(self.height.saturating_sub(1)))
+            .min(r);
+
+        self.frame_start_row = r - cursor_row;
+        self.cursor_row = cursor_row as u16;
+
+        if !self.rows.is_empty() {
+            self.center_horizontally();
+        }
+    }
+
     /// Get current line under cursor.
     pub fn current_line(&self) -> Option<&L> {
         self.lines.get(self.current_row())
@@ -94,7 +197,64 @@ where
 
     /// Get current row.
     pub fn current_row(&self) -> usize {
-        self.frame_start_row + usize::from(self.cursor_row)
+        self.rows
+            .get(self.current_visible_row())
+            .cloned()
+            .unwrap_or(0)
+    }
+
+    /// Fold the lines from `start` to `end` included so that only the `start`
+    /// line is visible followed by the given placeholder. If the cursor was on
+    /// one of the hidden lines then it's moved to the `start` line.
+    pub fn fold(&mut self, start: usize, end: usize, placeholder: String) {
+        if start >= end || end >= self.lines.len() {
+            return;
+        }
+
+        self.folds.insert(start, Fold { end, placeholder });
+        self.update_rows();
+    }
+
+    /// Unfold the fold starting at the given row, if any. Return whether the
+    /// fold existed.
+    pub fn unfold(&mut self, start: usize) -> bool {
+        let unfolded = self.folds.remove(&start).is_some();
+
+        if unfolded {
+            self.update_rows();
+        }
+
+        unfolded
+    }
+
+    /// Replace all the current folds with the given (start, end, placeholder)
+    /// ones. It's much faster than calling `fold` repeatedly.
+    pub fn refold(&mut self, folds: impl IntoIterator<Item = (usize, usize, String)>) {
+        self.folds.clear();
+
+        for (start, end, placeholder) in folds {
+            if start < end && end < self.lines.len() {
+                self.folds.insert(start, Fold { end, placeholder });
+            }
+        }
+
+        self.update_rows();
+    }
+
+    /// Remove all the folds.
+    pub fn unfold_all(&mut self) {
+        self.folds.clear();
+        self.update_rows();
+    }
+
+    /// Return whether there's a fold starting at the given row.
+    pub fn is_folded(&self, start: usize) -> bool {
+        self.folds.contains_key(&start)
+    }
+
+    /// Return whether the given row is hidden inside a fold.
+    pub fn is_hidden(&self, row: usize) -> bool {
+        self.rows.binary_search(&row).is_err()
     }
 
     /// Move the cursor one character to the right.
@@ -149,23 +309,246 @@ where
 
     /// Move the cursor down one row.
     pub fn move_down(&mut self) {
-        if self.current_row() + 1 >= self.lines.len() {
+        if self.current_visible_row() + 1 >= self.rows.len() {
             return;
         }
 
         self.cursor_row =
-            (usize::from(self.cursor_row + 1)).min(self.lines.len().saturating_sub(1)) as u16;
+            (usize::from(self.cursor_row + 1)).min(self.rows.len().saturating_sub(1)) as u16;
 
         if self.cursor_row >= self.height {
             self.cursor_row = self.height - 1;
             self.frame_start_row =
-                (self.frame_start_row + 1).min(self.lines.len().saturating_sub(1));
+                (self.frame_start_row + 1).min(self.rows.len().saturating_sub(1));
+        }
+
+        self.cap_line_char_ix();
+        self.center_horizontally();
+    }
+
+    /// Move the cursor to the start of the next word, possibly on one of the
+    /// following rows.
+    pub fn move_to_next_word(&mut self) {
+        let c = self.line_char_ix;
+        self.move_to_word(
+            |words| words.iter().find(|w| w.0 > c).map(|w| w.0),
+            |words| words.first().map(|w| w.0),
+        );
+    }
+
+    /// Move the cursor to the start of the previous word, possibly on one of
+    /// the previous rows.
+    pub fn move_to_prev_word(&mut self) {
+        if self.lines.is_empty() {
+            return;
+        }
+
+        let c = self.line_char_ix;
+        let vr = self.current_visible_row();
+
+        let mut found = self.lines[self.rows[vr]]
+            .words()
+            .iter()
+            .rev()
+            .find(|w| w.0 < c)
+            .map(|w| (vr, w.0));
+
+        let mut r = vr;
+        while found.is_none() && r > 0 {
+            r -= 1;
+            found = self.lines[self.rows[r]].words().last().map(|w| (r, w.0));
+        }
+
+        if let Some((r, c)) = found {
+            self.move_to_visible_row(r, c);
+        }
+    }
+
+    /// Move the cursor to the end of the word, or to the end of the next one if
+    /// it's already there, possibly on one of the following rows.
+    pub fn move_to_word_end(&mut self) {
+        let c = self.line_char_ix;
+        self.move_to_word(
+            |words| words.iter().find(|w| w.1 - 1 > c).map(|w| w.1 - 1),
+            |words| words.first().map(|w| w.1 - 1),
+        );
+    }
+
+    /// Move the cursor to the char `on_current_row` finds in the words of the
+    /// current row or, if there's none, to the one `on_next_rows` finds in the
+    /// first row after it where it finds any.
+    fn move_to_word(
+        &mut self,
+        on_current_row: impl Fn(&[(usize, usize)]) -> Option<usize>,
+        on_next_rows: impl Fn(&[(usize, usize)]) -> Option<usize>,
+    ) {
+        if self.lines.is_empty() {
+            return;
+        }
+
+        let vr = self.current_visible_row();
+
+        let mut found = on_current_row(&self.lines[self.rows[vr]].words()).map(|c| (vr, c));
+
+        let mut r = vr + 1;
+        while found.is_none() && r < self.rows.len() {
+            found = on_next_rows(&self.lines[self.rows[r]].words()).map(|c| (r, c));
+            r += 1;
+        }
+
+        if let Some((r, c)) = found {
+            self.move_to_visible_row(r, c);
         }
+    }
+
+    /// Scroll the view and the cursor down half a page.
+    pub fn half_page_down(&mut self) {
+        if self.lines.is_empty() {
+            return;
+        }
+
+        let half = usize::from(self.height / 2).max(1);
+        let vr = (self.current_visible_row() + half).min(self.rows.len() - 1);
+
+        self.frame_start_row = (self.frame_start_row + half).min(vr);
+        self.cursor_row = (vr - self.frame_start_row) as u16;
 
         self.cap_line_char_ix();
         self.center_horizontally();
     }
 
+    /// Scroll the view and the cursor up half a page.
+    pub fn half_page_up(&mut self) {
+        if self.lines.is_empty() {
+            return;
+        }
+
+        let half = usize::from(self.height / 2).max(1);
+        let vr = self.current_visible_row().saturating_sub(half);
+
+        self.frame_start_row = self.frame_start_row.saturating_sub(half);
+        self.cursor_row = (vr - self.frame_start_row) as u16;
+
+        self.cap_line_char_ix();
+        self.center_horizontally();
+    }
+
+    /// Scroll the view down one row keeping the cursor on the same row unless
+    /// it goes out of the screen.
+    pub fn scroll_down(&mut self) {
+        if self.frame_start_row + 1 >= self.rows.len() {
+            return;
+        }
+
+        self.frame_start_row += 1;
+        if self.cursor_row > 0 {
+            self.cursor_row -= 1;
+        } else {
+            self.cap_line_char_ix();
+            self.center_horizontally();
+        }
+    }
+
+    /// Scroll the view up one row keeping the cursor on the same row unless it
+    /// goes out of the screen.
+    pub fn scroll_up(&mut self) {
+        if self.frame_start_row == 0 {
+            return;
+        }
+
+        self.frame_start_row -= 1;
+        if self.cursor_row + 1 < self.height {
+            self.cursor_row += 1;
+        } else {
+            self.cap_line_char_ix();
+            self.center_horizontally();
+        }
+    }
+
+    /// Move the cursor to the first row on screen.
+    pub fn move_to_screen_top(&mut self) {
+        self.move_to_screen_row(|_| 0);
+    }
+
+    /// Move the cursor to the row in the middle of the screen.
+    pub fn move_to_screen_middle(&mut self) {
+        self.move_to_screen_row(|last| last / 2);
+    }
+
+    /// Move the cursor to the last row on screen.
+    pub fn move_to_screen_bottom(&mut self) {
+        self.move_to_screen_row(|last| last);
+    }
+
+    /// Move the cursor to the row on screen that `row` finds given the index
+    /// of the last row on screen.
+    fn move_to_screen_row(&mut self, row: impl Fn(usize) -> usize) {
+        if self.lines.is_empty() {
+            return;
+        }
+
+        let on_screen = self
+            .rows
+            .len()
+            .saturating_sub(self.frame_start_row)
+            .min(usize::from(self.height));
+
+        self.cursor_row = row(on_screen.saturating_sub(1)) as u16;
+
+        self.cap_line_char_ix();
+        self.center_horizontally();
+    }
+
+    /// Move the cursor to the given index into the visible rows and to the
+    /// given char scrolling as little as possible.
+    fn move_to_visible_row(&mut self, vr: usize, c: usize) {
+        let height = usize::from(self.height).max(1);
+
+        if vr < self.frame_start_row {
+            self.frame_start_row = vr;
+        } else if vr >= self.frame_start_row + height {
+            self.frame_start_row = vr + 1 - height;
+        }
+
+        self.cursor_row = (vr - self.frame_start_row) as u16;
+        self.line_char_ix = c;
+        self.max_line_char_ix = c;
+
+        self.cap_line_char_ix();
+        self.center_horizontally();
+    }
+
+    /// Map the given 0-based screen coordinates, relative to the top left
+    /// corner of the view, back to what the view shows there. Positions over
+    /// the line numbers are mapped to the first char shown of the line.
+    pub fn screen_position(&self, x: u16, y: u16) -> Option<ScreenPosition> {
+        if y >= self.height {
+            return None;
+        }
+
+        let r = *self.rows.get(self.frame_start_row + usize::from(y))?;
+        let l = &self.lines[r];
+
+        let x = usize::from(x).saturating_sub(self.num_column_width());
+
+        let mut w = 0;
+        for c in self.frame_start_char_ix..l.chars_count() {
+            w += usize::from(l.char_width(c));
+            if x < w {
+                return Some(ScreenPosition::Char(r, c));
+            }
+        }
+
+        // the placeholder is rendered right after the line
+        if let Some(fold) = self.folds.get(&r) {
+            if x < w + fold.placeholder.chars().count() {
+                return Some(ScreenPosition::Fold(r));
+            }
+        }
+
+        Some(ScreenPosition::Char(r, l.chars_count().saturating_sub(1)))
+    }
+
     /// Move to beginning of current line.
     pub fn move_to_sol(&mut self) {
         if self.lines.is_empty() {
@@ -217,8 +600,8 @@ where
         }
 
         self.frame_start_row += usize::from(self.height);
-        if self.current_row() >= self.lines.len() {
-            self.frame_start_row = self.lines.len() - 1;
+        if self.current_visible_row() >= self.rows.len() {
+            self.frame_start_row = self.rows.len() - 1;
             self.cursor_row = 0;
         }
 
@@ -226,13 +609,29 @@ where
         self.center_horizontally();
     }
 
-    /// Goto 0 based row and column.
+    /// Goto 0 based row and column. If the row is hidden inside some folds
+    /// then they're opened.
     pub fn goto(&mut self, r: usize, c: usize) {
         if self.lines.is_empty() {
             return;
         }
 
         let r = r.min(self.lines.len().saturating_sub(1));
+
+        let hiding_folds = self
+            .folds
+            .range(..r)
+            .filter(|(_, f)| f.end >= r)
+            .map(|(s, _)| *s)
+            .collect::<Vec<_>>();
+        if !hiding_folds.is_empty() {
+            for s in hiding_folds {
+                self.folds.remove(&s);
+            }
+            self.update_rows();
+        }
+
+        let r = self.visible_row(r);
         if r < self.frame_start_row || r >= self.frame_start_row + usize::from(self.height) {
             self.frame_start_row = r.saturating_sub(usize::from(self.height) / 2 - 1);
         }
@@ -250,6 +649,126 @@ where
         self.center_horizontally();
     }
 
+    /// Search all the non empty matches of the given regex and highlight them.
+    /// Return the number of matches.
+    pub fn search(&mut self, re: &Regex) -> usize {
+        self.matches.clear();
+
+        for (r, l) in self.lines.iter().enumerate() {
+            let text = l.text();
+
+            for m in re.find_iter(&text).filter(|m| m.start() < m.end()) {
+                let start = text[..m.start()].graphemes(true).count();
+                let end = text[..m.end()].graphemes(true).count();
+
+                self.matches.push((r, start, end));
+            }
+        }
+
+        self.matches.len()
+    }
+
+    /// Highlight the given (row, start_char_ix, end_char_ix) ranges as if they
+    /// were search matches.
+    pub fn set_matches(&mut self, mut matches: Vec<(usize, usize, usize)>) {
+        matches.sort_unstable();
+        matches.dedup();
+
+        self.matches = matches;
+    }
+
+    /// Remove all the search matches.
+    pub fn clear_search(&mut self) {
+        self.matches.clear();
+    }
+
+    /// Move the cursor to the start of the match after the cursor, or the one
+    /// before it if `forward` is false, wrapping around the end of the lines.
+    /// Return the index of the match.
+    pub fn next_match(&mut self, forward: bool) -> Option<usize> {
+        if self.matches.is_empty() {
+            return None;
+        }
+
+        let pos = (self.current_row(), self.line_char_ix);
+
+        let ix = if forward {
+            self.matches
+                .iter()
+                .position(|&(r, s, _)| (r, s) > pos)
+                .unwrap_or(0)
+        } else {
+            self.matches
+                .iter()
+                .rposition(|&(r, s, _)| (r, s) < pos)
+                .unwrap_or(self.matches.len() - 1)
+        };
+
+        let (r, c, _) = self.matches[ix];
+        self.goto(r, c);
+
+        Some(ix)
+    }
+
+    /// Return the index of the match under the cursor, if any, and the total
+    /// number of matches.
+    pub fn search_position(&self) -> (Option<usize>, usize) {
+        let row = self.current_row();
+        let col = self.line_char_ix;
+
+        let ix = self
+            .matches
+            .iter()
+            .position(|&(r, s, e)| r == row && s <= col && col < e);
+
+        (ix, self.matches.len())
+    }
+
+    fn current_visible_row(&self) -> usize {
+        self.frame_start_row + usize::from(self.cursor_row)
+    }
+
+    /// Return the index into `rows` of the given line or of the fold that
+    /// hides it.
+    fn visible_row(&self, r: usize) -> usize {
+        match self.rows.binary_search(&r) {
+            Ok(i) => i,
+            Err(i) => i.saturating_sub(1),
+        }
+    }
+
+    /// Recalculate the visible rows after the folds changed while keeping the
+    /// cursor on the same line, or on the fold that hides it, and at the same
+    /// position on screen.
+    fn update_rows(&mut self) {
+        let current_row = self.current_row();
+
+        self.rows.clear();
+
+        let mut r = 0;
+        while r < self.lines.len() {
+            self.rows.push(r);
+
+            r = match self.folds.get(&r) {
+                Some(f) => f.end + 1,
+                None => r + 1,
+            };
+        }
+
+        if self.rows.is_empty() {
+            return;
+        }
+
+        let r = self.visible_row(current_row);
+        let cursor_row = usize::from(self.cursor_row).min(r);
+
+        self.frame_start_row = r - cursor_row;
+        self.cursor_row = cursor_row as u16;
+
+        self.cap_line_char_ix();
+        self.center_horizontally();
+    }
+
     fn cap_line_char_ix(&mut self) {
         self.line_char_ix = self.max_line_char_ix.min(
             self.lines[self.current_row()]
@@ -259,7 +778,7 @@ where
     }
 
     fn center_horizontally(&mut self) {
-        let text_width = usize::from(self.width) - self.num_column_width();
+        let text_width = usize::from(self.width).saturating_sub(self.num_column_width());
 
         let row = &self.lines[self.current_row()];
         let row_len = row.chars_count();
@@ -293,6 +812,56 @@ where
         // +3 is because after the line number we show " | "
         self.num_lines_padding + 3
     }
+
+    /// Render the given row with the search matches highlighted.
+    fn render_line(&self, r: usize, l: &L, width: usize) -> String {
+        let first = self.matches.partition_point(|&(mr, _, _)| mr < r);
+
+        let mut rendered = String::new();
+        let mut col = self.frame_start_char_ix;
+        let mut width_left = width;
+
+        for &(_, s, e) in self.matches[first..].iter().take_while(|m| m.0 == r) {
+            if e <= col {
+                continue;
+            }
+
+            let s = s.max(col);
+
+            rendered.push_str(&render_range(l, col, s, &mut width_left));
+            if width_left == 0 {
+                return rendered;
+            }
+
+            rendered.push_str(&style::Invert.to_string());
+            rendered.push_str(&render_range(l, s, e, &mut width_left));
+            rendered.push_str(&style::NoInvert.to_string());
+
+            col = e;
+        }
+
+        rendered.push_str(&l.render(col, width_left));
+
+        rendered
+    }
+}
+
+/// Render the chars of the given line in the [start, end) range as long as
+/// they fit in `width_left` which is updated accordingly.
+fn render_range(l: &impl Line, start: usize, end: usize, width_left: &mut usize) -> String {
+    let mut w = 0;
+
+    for c in start..end.min(l.chars_count()) {
+        let cw = usize::from(l.char_width(c));
+        if w + cw > *width_left {
+            break;
+        }
+
+        w += cw;
+    }
+
+    *width_left -= w;
+    l.render(start, w)
 }
 
 impl<L> Widget for View<L>
@@ -308,11 +877,15 @@ where
 
         write!(term, "{}{}", cursor::Hide, cursor::Goto(1, 1))?;
 
-        let text_width = usize::from(self.width) - self.num_column_width();
+        let text_width = usize::from(self.width).saturating_sub(self.num_column_width());
 
         // always redraw all the lines possibly clearing them
         for i in 0..self.height {
-            let r = self.frame_start_row + usize::from(i);
+            let r = self
+                .rows
+                .get(self.frame_start_row + usize::from(i))
+                .cloned()
+                .unwrap_or(self.lines.len());
 
             match self.lines.get(r) {
                 None => write!(
@@ -335,7 +908,7 @@ where
                             r + 1,
                             fg,
                             color::Fg(color::Reset),
-                            l.render(self.frame_start_char_ix, text_width),
+                            self.render_line(r, l, text_width),
                             nlp = self.num_lines_padding,
                         )?
                     } else {
@@ -347,10 +920,26 @@ where
                             num_fg,
                             r + 1,
                             color::Fg(color::Reset),
-                            l.render(self.frame_start_char_ix, text_width),
+                            self.render_line(r, l, text_width),
                             nlp = self.num_lines_padding,
                         )?
                     }
+
+                    if let Some(fold) = self.folds.get(&r) {
+                        let line_width = (self.frame_start_char_ix..l.chars_count())
+                            .map(|c| usize::from(l.char_width(c)))
+                            .sum::<usize>();
+
+                        write!(
+                            term,
+                            "{}{}",
+                            color::Fg(color::LightBlack),
+                            fold.placeholder
+                                .chars()
+                                .take(text_width.saturating_sub(line_width))
+                                .collect::<String>(),
+                        )?;
+                    }
                 }
             }
 
@@ -377,16 +966,19 @@ where
 
 #[cfg(test)]
 mod tests {
-    use crate::widgets::ascii_line::AsciiLine;
+    use regex::Regex;
+    use termion::style;
 
-    use super::{Line, View};
+    use crate::widgets::unicode_line::UnicodeLine;
+
+    use super::{Line, ScreenPosition, View};
 
     #[test]
     fn test_basic_movement() {
         let mut lines = vec![
-            AsciiLine::new("hello world!").unwrap(),
-            AsciiLine::new("").unwrap(),
-            AsciiLine::new("and universe!").unwrap(),
+            UnicodeLine::new("hello world!"),
+            UnicodeLine::new(""),
+            UnicodeLine::new("and universe!"),
         ];
 
         let mut view = View::new((80, 23), lines.clone());
@@ -434,7 +1026,7 @@ mod tests {
 
     #[test]
     fn test_basic_horizontal_framing() {
-        let mut lines = vec![AsciiLine::new("hello world!").unwrap()];
+        let mut lines = vec![UnicodeLine::new("hello world!")];
 
         let mut view = View::new((9, 3), lines.clone());
 
@@ -486,10 +1078,10 @@ mod tests {
     #[test]
     fn test_basic_vertical_framing() {
         let mut lines = vec![
-            AsciiLine::new("hello world!").unwrap(),
-            AsciiLine::new("hello!").unwrap(),
-            AsciiLine::new("ciao!").unwrap(),
-            AsciiLine::new("hi!").unwrap(),
+            UnicodeLine::new("hello world!"),
+            UnicodeLine::new("hello!"),
+            UnicodeLine::new("ciao!"),
+            UnicodeLine::new("hi!"),
         ];
 
         let mut view = View::new((80, 2), lines.clone());
@@ -526,9 +1118,43 @@ mod tests {
         assert_eq!(view.frame_start_row, 0);
     }
 
+    #[test]
+    fn test_resize() {
+        let lines = (0..10)
+            .map(|i| UnicodeLine::new(format!("line {} is quite long", i)))
+            .collect::<Vec<_>>();
+
+        let mut view = View::new((80, 8), lines);
+        view.goto(6, 18);
+        assert_eq!(view.frame_start_row, 0);
+        assert_eq!(view.cursor_row, 6);
+        assert_eq!(view.frame_start_char_ix, 0);
+
+        view.resize((12, 3));
+        assert_eq!(view.current_row(), 6);
+        assert_eq!(view.col(), 18);
+        assert_eq!(view.frame_start_row, 4);
+        assert_eq!(view.cursor_row, 2);
+        assert_eq!(view.frame_start_char_ix, 12);
+        assert_eq!(view.cursor_col, 6);
+
+        view.resize((80, 8));
+        assert_eq!(view.current_row(), 6);
+        assert_eq!(view.frame_start_row, 4);
+        assert_eq!(view.cursor_row, 2);
+
+        view.resize((80, 0));
+        view.move_down();
+        assert_eq!(view.current_row(), 7);
+        assert_eq!(view.cursor_row, 1);
+        view.goto(2, 0);
+        assert_eq!(view.current_row(), 2);
+        assert_eq!(view.frame_start_row, 2);
+    }
+
     #[test]
     fn test_sol() {
-        let mut lines = vec![AsciiLine::new("hello world!").unwrap()];
+        let mut lines = vec![UnicodeLine::new("hello world!")];
         let mut view = View::new((80, 23), lines.clone());
 
         for l in &mut lines {
@@ -549,7 +1175,7 @@ mod tests {
 
     #[test]
     fn test_eol() {
-        let mut lines = vec![AsciiLine::new("hello world!").unwrap()];
+        let mut lines = vec![UnicodeLine::new("hello world!")];
         let mut view = View::new((80, 23), lines.clone());
 
         for l in &mut lines {
@@ -571,12 +1197,12 @@ mod tests {
     #[test]
     fn test_paging() {
         let mut lines = vec![
-            AsciiLine::new("line 1").unwrap(),
-            AsciiLine::new("line 2").unwrap(),
-            AsciiLine::new("line 3").unwrap(),
-            AsciiLine::new("line 4").unwrap(),
-            AsciiLine::new("line 5").unwrap(),
-            AsciiLine::new("line 6").unwrap(),
+            UnicodeLine::new("line 1"),
+            UnicodeLine::new("line 2"),
+            UnicodeLine::new("line 3"),
+            UnicodeLine::new("line 4"),
+            UnicodeLine::new("line 5"),
+            UnicodeLine::new("line 6"),
         ];
         let mut view = View::new((80, 3), lines.clone());
 
@@ -611,16 +1237,160 @@ mod tests {
     }
 
     #[test]
-    #[allow(clippy::cyclomatic_complexity)]
+    fn test_half_paging_and_scrolling() {
+        let lines = (1..=10).map(|i| UnicodeLine::new(format!("line {}", i)));
+        let mut view = View::new((80, 4), lines);
+
+        view.half_page_down();
+        assert_eq!((view.current_row(), view.frame_start_row), (2, 2));
+
+        view.move_down();
+        view.half_page_down();
+        view.half_page_down();
+        assert_eq!((view.current_row(), view.frame_start_row), (7, 6));
+
+        view.half_page_down();
+        assert_eq!((view.current_row(), view.frame_start_row), (9, 8));
+
+        view.half_page_up();
+        assert_eq!((view.current_row(), view.frame_start_row), (7, 6));
+
+        view.goto(0, 0);
+        view.scroll_down();
+        assert_eq!((view.current_row(), view.frame_start_row), (1, 1));
+
+        view.move_down();
+        view.scroll_down();
+        assert_eq!((view.current_row(), view.frame_start_row), (2, 2));
+
+        view.scroll_up();
+        view.scroll_up();
+        assert_eq!((view.current_row(), view.frame_start_row), (2, 0));
+        view.scroll_up();
+        assert_eq!((view.current_row(), view.frame_start_row), (2, 0));
+
+        view.move_down();
+        view.scroll_up();
+        assert_eq!(view.current_row(), 3);
+        view.goto(5, 0);
+        view.scroll_up();
+        view.scroll_up();
+        assert_eq!((view.current_row(), view.frame_start_row), (5, 2));
+        view.scroll_up();
+        assert_eq!((view.current_row(), view.frame_start_row), (4, 1));
+    }
+
+    #[test]
+    fn test_screen_motions() {
+        let lines = (1..=10).map(|i| UnicodeLine::new(format!("line {}", i)));
+        let mut view = View::new((80, 5), lines);
+
+        view.goto(6, 0);
+        let frame_start_row = view.frame_start_row;
+
+        view.move_to_screen_top();
+        assert_eq!(view.current_row(), frame_start_row);
+        view.move_to_screen_middle();
+        assert_eq!(view.current_row(), frame_start_row + 2);
+        view.move_to_screen_bottom();
+        assert_eq!(view.current_row(), frame_start_row + 4);
+        assert_eq!(view.frame_start_row, frame_start_row);
+
+        let mut view = View::new((80, 5), vec![UnicodeLine::new("a"), UnicodeLine::new("b")]);
+        view.move_to_screen_bottom();
+        assert_eq!(view.current_row(), 1);
+        view.move_to_screen_middle();
+        assert_eq!(view.current_row(), 0);
+    }
+
+    #[test]
+    fn test_word_motions() {
+        let lines = vec![
+            UnicodeLine::new("foo.bar  baz_1"),
+            UnicodeLine::new(""),
+            UnicodeLine::new("  x"),
+        ];
+        let mut view = View::new((80, 23), lines);
+
+        assert_eq!(
+            view.current_line().unwrap().words(),
+            vec![(0, 3), (3, 4), (4, 7), (9, 14)]
+        );
+
+        let mut positions = vec![];
+        for _ in 0..5 {
+            view.move_to_next_word();
+            positions.push((view.current_row(), view.col()));
+        }
+        assert_eq!(positions, vec![(0, 3), (0, 4), (0, 9), (2, 2), (2, 2)]);
+
+        positions.clear();
+        for _ in 0..5 {
+            view.move_to_prev_word();
+            positions.push((view.current_row(), view.col()));
+        }
+        assert_eq!(positions, vec![(0, 9), (0, 4), (0, 3), (0, 0), (0, 0)]);
+
+        positions.clear();
+        for _ in 0..5 {
+            view.move_to_word_end();
+            positions.push((view.current_row(), view.col()));
+        }
+        assert_eq!(positions, vec![(0, 2), (0, 3), (0, 6), (0, 13), (2, 2)]);
+
+        // folded lines are skipped
+        view.goto(0, 13);
+        view.fold(1, 2, String::new());
+        view.move_to_next_word();
+        assert_eq!((view.current_row(), view.col()), (0, 13));
+    }
+
+    #[test]
+    fn test_screen_position() {
+        let lines = vec![
+            UnicodeLine::new("a\tb"),
+            UnicodeLine::new("{"),
+            UnicodeLine::new("}"),
+            UnicodeLine::new("end"),
+        ];
+        let mut view = View::new((80, 3), lines);
+        view.fold(1, 2, "...".to_string());
+
+        // the line numbers take 4 columns and the tab 3
+        assert_eq!(view.screen_position(0, 0), Some(ScreenPosition::Char(0, 0)));
+        assert_eq!(view.screen_position(4, 0), Some(ScreenPosition::Char(0, 0)));
+        assert_eq!(view.screen_position(5, 0), Some(ScreenPosition::Char(0, 1)));
+        assert_eq!(view.screen_position(7, 0), Some(ScreenPosition::Char(0, 1)));
+        assert_eq!(view.screen_position(8, 0), Some(ScreenPosition::Char(0, 2)));
+        assert_eq!(
+            view.screen_position(50, 0),
+            Some(ScreenPosition::Char(0, 2))
+        );
+
+        assert_eq!(view.screen_position(4, 1), Some(ScreenPosition::Char(1, 0)));
+        assert_eq!(view.screen_position(5, 1), Some(ScreenPosition::Fold(1)));
+        assert_eq!(view.screen_position(7, 1), Some(ScreenPosition::Fold(1)));
+        assert_eq!(view.screen_position(8, 1), Some(ScreenPosition::Char(1, 0)));
+
+        assert_eq!(view.screen_position(4, 2), Some(ScreenPosition::Char(3, 0)));
+        assert_eq!(view.screen_position(4, 3), None);
+
+        view.page_down();
+        assert_eq!(view.screen_position(4, 0), Some(ScreenPosition::Char(3, 0)));
+        assert_eq!(view.screen_position(4, 1), None);
+    }
+
+    #[test]
+    #[allow(clippy::cognitive_complexity)]
     fn test_goto() {
         let mut lines = vec![
-            AsciiLine::new("a very long line").unwrap(),
-            AsciiLine::new("").unwrap(),
-            AsciiLine::new("line 3").unwrap(),
-            AsciiLine::new("line 4").unwrap(),
-            AsciiLine::new("line 5").unwrap(),
-            AsciiLine::new("-------------------------------------------------------").unwrap(),
-            AsciiLine::new("").unwrap(),
+            UnicodeLine::new("a very long line"),
+            UnicodeLine::new(""),
+            UnicodeLine::new("line 3"),
+            UnicodeLine::new("line 4"),
+            UnicodeLine::new("line 5"),
+            UnicodeLine::new("-------------------------------------------------------"),
+            UnicodeLine::new(""),
         ];
         let mut view = View::new((20, 4), lines.clone());
 
@@ -674,13 +1444,13 @@ mod tests {
     #[test]
     fn test_remembers_max_col() {
         let mut lines = vec![
-            AsciiLine::new("a very long line").unwrap(),
-            AsciiLine::new("").unwrap(),
-            AsciiLine::new("line 3").unwrap(),
-            AsciiLine::new("line 4").unwrap(),
-            AsciiLine::new("line 5").unwrap(),
-            AsciiLine::new("-------------------------------------------------------").unwrap(),
-            AsciiLine::new("").unwrap(),
+            UnicodeLine::new("a very long line"),
+            UnicodeLine::new(""),
+            UnicodeLine::new("line 3"),
+            UnicodeLine::new("line 4"),
+            UnicodeLine::new("line 5"),
+            UnicodeLine::new("-------------------------------------------------------"),
+            UnicodeLine::new(""),
         ];
         let mut view = View::new((20, 4), lines.clone());
 
@@ -729,9 +1499,9 @@ mod tests {
     #[test]
     fn test_tab_movement() {
         let mut lines = vec![
-            AsciiLine::new("line\tfuffa").unwrap(),
-            AsciiLine::new("line 3").unwrap(),
-            AsciiLine::new("line 4").unwrap(),
+            UnicodeLine::new("line\tfuffa"),
+            UnicodeLine::new("line 3"),
+            UnicodeLine::new("line 4"),
         ];
         let mut view = View::new((80, 23), lines.clone());
 
@@ -768,4 +1538,135 @@ mod tests {
         assert_eq!(view.current_line().unwrap(), &lines[0]);
     }
 
+    #[test]
+    fn test_folds() {
+        let mut lines = (0..10)
+            .map(|i| UnicodeLine::new(format!("line {}", i)))
+            .collect::<Vec<_>>();
+        let mut view = View::new((80, 4), lines.clone());
+
+        for l in &mut lines {
+            l.indent(5);
+        }
+
+        view.goto(3, 2);
+        view.fold(1, 4, "...".to_string());
+        assert!(view.is_folded(1));
+        assert_eq!(view.current_row(), 1);
+        assert_eq!(view.col(), 2);
+
+        view.move_down();
+        assert_eq!(view.current_row(), 5);
+        assert_eq!(view.current_line().unwrap(), &lines[5]);
+
+        view.fold(6, 8, "...".to_string());
+        view.move_down();
+        assert_eq!(view.current_row(), 6);
+        view.move_down();
+        assert_eq!(view.current_row(), 9);
+        view.move_down();
+        assert_eq!(view.current_row(), 9);
+        assert_eq!(view.frame_start_row, 1);
+
+        view.move_up();
+        view.move_up();
+        view.move_up();
+        assert_eq!(view.current_row(), 1);
+
+        // nested folds are remembered when the outer one is opened
+        view.fold(0, 9, "...".to_string());
+        assert_eq!(view.current_row(), 0);
+        view.move_down();
+        assert_eq!(view.current_row(), 0);
+
+        assert!(view.unfold(0));
+        assert!(!view.unfold(0));
+        view.move_down();
+        assert_eq!(view.current_row(), 1);
+        view.move_down();
+        assert_eq!(view.current_row(), 5);
+
+        // goto opens the folds hiding the row
+        view.goto(7, 0);
+        assert_eq!(view.current_row(), 7);
+        assert!(!view.is_folded(6));
+        assert!(view.is_folded(1));
+
+        view.unfold_all();
+        assert!(!view.is_folded(1));
+        assert_eq!(view.current_row(), 7);
+        view.move_up();
+        assert_eq!(view.current_row(), 6);
+
+        view.refold(vec![(0, 2, "...".to_string()), (5, 9, "...".to_string())]);
+        assert_eq!(view.current_row(), 5);
+        view.move_up();
+        assert_eq!(view.current_row(), 4);
+        view.move_up();
+        view.move_up();
+        assert_eq!(view.current_row(), 0);
+    }
+
+    #[test]
+    fn test_search() {
+        let lines = vec![
+            UnicodeLine::new("the cat sat on the mat"),
+            UnicodeLine::new(""),
+            UnicodeLine::new("the end"),
+        ];
+        let mut view = View::new((80, 23), lines);
+
+        assert_eq!(view.search(&Regex::new("the").unwrap()), 3);
+        assert_eq!(view.search_position(), (Some(0), 3));
+
+        assert_eq!(view.next_match(true), Some(1));
+        assert_eq!((view.current_row(), view.col()), (0, 15));
+        assert_eq!(view.search_position(), (Some(1), 3));
+
+        assert_eq!(view.next_match(true), Some(2));
+        assert_eq!((view.current_row(), view.col()), (2, 0));
+
+        assert_eq!(view.next_match(true), Some(0));
+        assert_eq!((view.current_row(), view.col()), (0, 0));
+
+        assert_eq!(view.next_match(false), Some(2));
+        assert_eq!(view.next_match(false), Some(1));
+
+        view.move_left();
+        assert_eq!(view.search_position(), (None, 3));
+
+        assert_eq!(view.search(&Regex::new("[cm]at").unwrap()), 2);
+        assert_eq!(view.next_match(false), Some(0));
+        assert_eq!((view.current_row(), view.col()), (0, 4));
+        assert_eq!(view.next_match(false), Some(1));
+        assert_eq!((view.current_row(), view.col()), (0, 19));
+
+        // empty matches are ignored
+        assert_eq!(view.search(&Regex::new("x*").unwrap()), 0);
+        assert_eq!(view.next_match(true), None);
+
+        view.search(&Regex::new("the").unwrap());
+        view.clear_search();
+        assert_eq!(view.search_position(), (None, 0));
+    }
+
+    #[test]
+    fn test_render_matches() {
+        let lines = vec![UnicodeLine::new("the cat sat on the mat")];
+        let mut view = View::new((80, 23), lines);
+
+        view.search(&Regex::new("at").unwrap());
+
+        let l = view.lines[0].clone();
+        assert_eq!(
+            view.render_line(0, &l, 12),
+            format!(
+                "the c{}at{} s{}at{} ",
+                style::Invert,
+                style::NoInvert,
+                style::Invert,
+                style::NoInvert
+            )
+        );
+    }
 }